@@ -1,12 +1,34 @@
-use alloc::{string::String, vec::Vec};
-use genio::{Read, Write, bufio::BufRead};
+use alloc::{rc::Rc, string::String, vec, vec::Vec};
+use core::{
+    cell::{LazyCell, RefCell},
+    time::Duration,
+};
+use genio::{
+    Read, Write,
+    bufio::{BufRead, BufReadProgress},
+    error::BufError,
+};
 use lilium_sys::sys::{
+    fs::{
+        ACCESS_READ, ACCESS_WRITE, CloseFile, FILE_KIND_CHAR_DEVICE, FILE_KIND_DIRECTORY,
+        FILE_KIND_REGULAR, FILE_KIND_SYMLINK, FileHandle, FileInfo, FileOpenOptions, GetFileInfo,
+        OP_APPEND, OP_CREATE, OP_DIRECTORY_ACCESS, OP_NO_RESOLVE_LINK, OP_STREAM_ACCESS,
+        OP_TRUNCATE, OpenFile,
+    },
     handle::HandlePtr,
-    io::{__HANDLE_IO_STDERR, __HANDLE_IO_STDIN, __HANDLE_IO_STDOUT, IOHandle, IORead, IOWrite},
+    io::{
+        __HANDLE_IO_STDERR, __HANDLE_IO_STDIN, __HANDLE_IO_STDOUT, IOHandle, IORead, IOSeek,
+        IOWrite, MODE_BLOCKING, MODE_NONBLOCKING, SEEK_FROM_CURRENT, SEEK_FROM_END,
+        SEEK_FROM_START, SetIOBlockingMode,
+    },
+    kstr::{KCSlice, KStrCPtr},
 };
+use memchr::memrchr;
 
+mod bytes;
 mod error;
 
+pub use bytes::{decode_bytes, encode_bytes, kstr, raw_byte};
 pub use error::{Error, ErrorKind};
 
 pub type Result<T> = core::result::Result<T, Error>;
@@ -15,27 +37,477 @@ pub struct Stdio(HandlePtr<IOHandle>);
 
 impl core::fmt::Write for Stdio {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
-        if unsafe { IOWrite(self.0, s.as_ptr().cast(), s.len() as _) } < 0 {
-            Err(core::fmt::Error)
+        self.write_all(s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+impl Stdio {
+    /// Switches between reads that wait for input, and reads that fail with [`ErrorKind::WouldBlock`] when none is ready
+    pub fn set_blocking(&self, blocking: bool) -> Result<()> {
+        let mode = if blocking {
+            MODE_BLOCKING
+        } else {
+            MODE_NONBLOCKING
+        };
+        let res = unsafe { SetIOBlockingMode(self.0, mode) };
+        if res < 0 {
+            Err(Error::from_raw_os_error(res))
         } else {
             Ok(())
         }
     }
 }
 
+/// A handle that one or more of the shell's descriptors refer to
+pub enum OpenHandle {
+    /// One of the standard streams that the shell was given, which it never closes
+    Inherited(HandlePtr<IOHandle>),
+    /// A file that the shell opened itself, which is closed once no descriptor refers to it
+    File(File),
+}
+
+impl OpenHandle {
+    fn hdl(&self) -> HandlePtr<IOHandle> {
+        match self {
+            OpenHandle::Inherited(hdl) => *hdl,
+            OpenHandle::File(file) => file.0.cast(),
+        }
+    }
+}
+
+/// The shell's descriptors, indexed by number, which start out as the standard streams that the shell was given
+#[thread_local]
+static FDS: LazyCell<RefCell<Vec<Option<Rc<OpenHandle>>>>> = LazyCell::new(|| {
+    let std = [__HANDLE_IO_STDIN, __HANDLE_IO_STDOUT, __HANDLE_IO_STDERR];
+    RefCell::new(
+        std.into_iter()
+            .map(|hdl| Some(Rc::new(OpenHandle::Inherited(hdl))))
+            .collect(),
+    )
+});
+
+/// Gets the handle that descriptor `fd` refers to, if it is open
+pub fn get_fd(fd: u32) -> Option<Rc<OpenHandle>> {
+    FDS.borrow().get(fd as usize).cloned().flatten()
+}
+
+/// The open descriptors and their handles, in order, which are what a program started by the shell is given.
+///
+/// Handles that the shell only uses internally, such as the script it is reading or the directories in `PATH`, are never in the table,
+/// so programs don't inherit them.
+pub fn open_fds() -> Vec<(u32, HandlePtr<IOHandle>)> {
+    FDS.borrow()
+        .iter()
+        .enumerate()
+        .filter_map(|(fd, hdl)| Some((fd as u32, hdl.as_ref()?.hdl())))
+        .collect()
+}
+
+/// Makes descriptor `fd` refer to `hdl`, or closes it if `hdl` is `None`, returning what it referred to before
+pub fn set_fd(fd: u32, hdl: Option<Rc<OpenHandle>>) -> Option<Rc<OpenHandle>> {
+    // Output from before the change goes where it was meant to
+    flush_output();
+    let mut fds = FDS.borrow_mut();
+    let n = fd as usize;
+    if n >= fds.len() {
+        if hdl.is_none() {
+            return None;
+        }
+        fds.resize(n + 1, None);
+    }
+    let old = core::mem::replace(&mut fds[n], hdl);
+    drop(fds);
+
+    // The shell's own output follows the descriptor, so whether it is line buffered does too
+    if let Some(out) = OUTPUT.get(n.wrapping_sub(1))
+        && let Ok(mut out) = out.try_borrow_mut()
+    {
+        out.line_buffered = is_terminal(fd);
+    }
+    old
+}
+
+/// The stream for descriptor `fd`, where a closed descriptor has a null handle so that using it fails
+fn fd_stream(fd: u32) -> Stdio {
+    Stdio(get_fd(fd).map_or(HandlePtr::null(), |hdl| hdl.hdl()))
+}
+
 #[inline(always)]
 pub fn stdin() -> Stdio {
-    Stdio(__HANDLE_IO_STDIN)
+    fd_stream(0)
 }
 
-#[inline(always)]
-pub fn stdout() -> Stdio {
-    Stdio(__HANDLE_IO_STDOUT)
+/// Writes to whatever descriptor `fd` refers to at the time of each write, so that it follows redirections
+pub struct Fd(u32);
+
+impl Write for Fd {
+    type WriteError = Error;
+    type FlushError = Error;
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        fd_stream(self.0).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        fd_stream(self.0).flush()
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        fd_stream(self.0).write_all(buf)
+    }
+
+    fn size_hint(&mut self, _: usize) {}
+}
+
+/// Whether descriptor `fd` refers to a character device, such as a terminal
+fn is_terminal(fd: u32) -> bool {
+    get_fd(fd).is_some_and(|hdl| {
+        Metadata::from_handle(hdl.hdl().cast())
+            .is_ok_and(|meta| meta.file_type() == FileType::CharDevice)
+    })
+}
+
+/// The shell's own output to stdout and stderr, which is buffered a line at a time when it goes to a terminal
+#[thread_local]
+static OUTPUT: LazyCell<[RefCell<BufWriter<Fd>>; 2]> = LazyCell::new(|| {
+    [1, 2].map(|fd| {
+        let mut out = BufWriter::new(Fd(fd));
+        out.line_buffered = is_terminal(fd);
+        RefCell::new(out)
+    })
+});
+
+/// Calls `f` with the buffered writer for the shell's stdout, which is what [`print!`] and [`println!`] write to
+pub fn with_stdout<R>(f: impl FnOnce(&mut BufWriter<Fd>) -> R) -> R {
+    f(&mut OUTPUT[0].borrow_mut())
+}
+
+/// Calls `f` with the buffered writer for the shell's stderr, which is what [`eprint!`] and [`eprintln!`] write to
+pub fn with_stderr<R>(f: impl FnOnce(&mut BufWriter<Fd>) -> R) -> R {
+    f(&mut OUTPUT[1].borrow_mut())
+}
+
+/// Takes the first error from writing the shell's stdout since the last call, such as [`ErrorKind::BrokenPipe`] once a pipe is closed
+pub fn take_stdout_error() -> Option<Error> {
+    let e = OUTPUT[0].borrow_mut().take_error()?;
+    // Lilium reports the reader closing its end as `ClosedRemotely`, which for a command's output is a broken pipe
+    if e.kind() == ErrorKind::ConnectionReset {
+        Some(Error::new_with_message(
+            ErrorKind::BrokenPipe,
+            "Broken pipe",
+        ))
+    } else {
+        Some(e)
+    }
+}
+
+/// Writes out the shell's buffered output.
+///
+/// This is needed before the descriptors change, before a program is started or the shell exits, and before waiting for input after a prompt.
+pub fn flush_output() {
+    for out in OUTPUT.iter() {
+        // A writer that is in use, such as when panicking in the middle of a write, is left alone
+        if let Ok(mut out) = out.try_borrow_mut()
+            && let Err(e) = out.flush()
+        {
+            out.error.get_or_insert(e);
+        }
+    }
 }
 
 #[inline(always)]
 pub fn stderr() -> Stdio {
-    Stdio(__HANDLE_IO_STDERR)
+    fd_stream(2)
+}
+
+/// The options for opening a file with the given access and operation modes, which blocks and uses the file's main stream
+fn open_options(access_mode: u32, op_mode: u32) -> FileOpenOptions {
+    FileOpenOptions {
+        stream_override: KStrCPtr::empty(),
+        access_mode,
+        op_mode,
+        create_acl: HandlePtr::null(),
+        blocking_mode: MODE_BLOCKING,
+        extended_options: KCSlice::empty(),
+    }
+}
+
+/// Opens the file at `path` with `options`, looking a relative `path` up in the directory `base`.
+///
+/// A null `base` stands for the current directory, and `path` may hold bytes that aren't UTF-8, as from [`decode_bytes`].
+fn open_with_options(
+    base: HandlePtr<FileHandle>,
+    path: &str,
+    options: &FileOpenOptions,
+) -> Result<HandlePtr<FileHandle>> {
+    let path = encode_bytes(path);
+    let mut hdl = HandlePtr::null();
+    let res = unsafe { OpenFile(&mut hdl, base, kstr(&path), options) };
+
+    if res < 0 {
+        Err(Error::from_raw_os_error(res))
+    } else {
+        Ok(hdl)
+    }
+}
+
+/// Opens the file at `path` with the given access and operation modes, as [`open_with_options`] does with [`open_options`]
+fn open_file(
+    base: HandlePtr<FileHandle>,
+    path: &str,
+    access_mode: u32,
+    op_mode: u32,
+) -> Result<HandlePtr<FileHandle>> {
+    open_with_options(base, path, &open_options(access_mode, op_mode))
+}
+
+/// Where [`File::seek`] moves to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    /// An offset from the start of the file
+    Start(u64),
+    /// An offset from the end of the file
+    End(i64),
+    /// An offset from the current position
+    Current(i64),
+}
+
+/// An open file, which is closed when it is dropped
+pub struct File(HandlePtr<FileHandle>);
+
+impl File {
+    /// Opens the file at `path` for reading
+    pub fn open(path: &str) -> Result<Self> {
+        open_file(HandlePtr::null(), path, ACCESS_READ, OP_STREAM_ACCESS).map(Self)
+    }
+
+    /// Opens the file at `path` for writing, creating it or emptying it
+    pub fn create(path: &str) -> Result<Self> {
+        let op_mode = OP_STREAM_ACCESS | OP_CREATE | OP_TRUNCATE;
+        open_file(HandlePtr::null(), path, ACCESS_WRITE, op_mode).map(Self)
+    }
+
+    /// Opens the file at `path` for writing to its end, creating it if it doesn't exist
+    pub fn append(path: &str) -> Result<Self> {
+        let op_mode = OP_STREAM_ACCESS | OP_CREATE | OP_APPEND;
+        open_file(HandlePtr::null(), path, ACCESS_WRITE, op_mode).map(Self)
+    }
+
+    /// Opens the file at `path` with any of the options that Lilium supports, such as another stream or non-blocking access
+    pub fn open_with(path: &str, options: &FileOpenOptions) -> Result<Self> {
+        open_with_options(HandlePtr::null(), path, options).map(Self)
+    }
+
+    /// Queries the metadata of the file
+    pub fn metadata(&self) -> Result<Metadata> {
+        Metadata::from_handle(self.0)
+    }
+
+    /// Moves the position that the file is read and written at, returning the new position from the start of the file
+    pub fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let (from, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                let offset = i64::try_from(offset).map_err(|_| {
+                    Error::new_with_message(ErrorKind::InvalidInput, "seek offset is too large")
+                })?;
+                (SEEK_FROM_START, offset)
+            }
+            SeekFrom::End(offset) => (SEEK_FROM_END, offset),
+            SeekFrom::Current(offset) => (SEEK_FROM_CURRENT, offset),
+        };
+        let mut new_pos = 0;
+        let res = unsafe { IOSeek(self.0.cast(), from, offset, &mut new_pos) };
+        if res < 0 {
+            Err(Error::from_raw_os_error(res))
+        } else {
+            Ok(new_pos)
+        }
+    }
+
+    /// Gives up ownership of the handle, which is no longer closed when the `File` is dropped
+    pub fn into_handle(self) -> HandlePtr<FileHandle> {
+        let hdl = self.0;
+        core::mem::forget(self);
+        hdl
+    }
+
+    fn stream(&self) -> Stdio {
+        Stdio(self.0.cast())
+    }
+}
+
+impl Read for File {
+    type ReadError = Error;
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.stream().read(buf)
+    }
+}
+
+impl Write for File {
+    type WriteError = Error;
+    type FlushError = Error;
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.stream().write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.stream().write_all(buf)
+    }
+
+    fn size_hint(&mut self, _: usize) {}
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        unsafe {
+            CloseFile(self.0);
+        }
+    }
+}
+
+/// How a redirection opens a file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OpenMode {
+    /// `<`
+    Read,
+    /// `>`, which creates the file or empties it
+    Write,
+    /// `>>`, which creates the file or writes to the end of it
+    Append,
+    /// `<>`, which creates the file without emptying it
+    ReadWrite,
+}
+
+/// Opens the file at `path` for a redirection
+pub fn open_redirect(path: &str, mode: OpenMode) -> Result<OpenHandle> {
+    let file = match mode {
+        OpenMode::Read => File::open(path)?,
+        OpenMode::Write => File::create(path)?,
+        OpenMode::Append => File::append(path)?,
+        // `<>` creates the file without emptying it
+        OpenMode::ReadWrite => {
+            let options = open_options(ACCESS_READ | ACCESS_WRITE, OP_STREAM_ACCESS | OP_CREATE);
+            File::open_with(path, &options)?
+        }
+    };
+    Ok(OpenHandle::File(file))
+}
+
+/// Opens the directory at `path`, so that files can be looked up relative to it
+pub fn open_dir(path: &str) -> Result<HandlePtr<FileHandle>> {
+    open_file(HandlePtr::null(), path, ACCESS_READ, OP_DIRECTORY_ACCESS)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FileType {
+    File,
+    Directory,
+    Symlink,
+    CharDevice,
+    Other,
+}
+
+/// Information about a file, as reported by the filesystem
+#[derive(Copy, Clone, Debug)]
+pub struct Metadata {
+    file_type: FileType,
+    len: u64,
+    modified: Duration,
+}
+
+impl Metadata {
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.file_type == FileType::File
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.file_type == FileType::Directory
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.file_type == FileType::Symlink
+    }
+
+    /// The size of the file's data, in bytes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// The time the file was last modified, since the Unix epoch
+    pub fn modified(&self) -> Duration {
+        self.modified
+    }
+
+    fn from_handle(hdl: HandlePtr<FileHandle>) -> Result<Self> {
+        let mut info: FileInfo = bytemuck::zeroed();
+        let res = unsafe { GetFileInfo(hdl, &mut info) };
+        if res < 0 {
+            return Err(Error::from_raw_os_error(res));
+        }
+
+        Ok(Self {
+            file_type: match info.kind {
+                FILE_KIND_REGULAR => FileType::File,
+                FILE_KIND_DIRECTORY => FileType::Directory,
+                FILE_KIND_SYMLINK => FileType::Symlink,
+                FILE_KIND_CHAR_DEVICE => FileType::CharDevice,
+                _ => FileType::Other,
+            },
+            len: info.size,
+            modified: Duration::new(info.modified_secs.max(0) as u64, info.modified_nanos),
+        })
+    }
+}
+
+fn path_metadata(base: HandlePtr<FileHandle>, path: &str, op_mode: u32) -> Result<Metadata> {
+    let hdl = open_file(base, path, 0, op_mode)?;
+    let meta = Metadata::from_handle(hdl);
+    unsafe {
+        CloseFile(hdl);
+    }
+    meta
+}
+
+/// Queries the metadata of the file at `path`, following symbolic links
+pub fn metadata(path: &str) -> Result<Metadata> {
+    path_metadata(HandlePtr::null(), path, OP_STREAM_ACCESS)
+}
+
+/// Queries the metadata of the file at `path` relative to the directory `dir`, following symbolic links
+pub fn metadata_at(dir: HandlePtr<FileHandle>, path: &str) -> Result<Metadata> {
+    path_metadata(dir, path, OP_STREAM_ACCESS)
+}
+
+/// Queries the metadata of the file at `path`, or of the symbolic link itself if `path` names one
+pub fn symlink_metadata(path: &str) -> Result<Metadata> {
+    path_metadata(HandlePtr::null(), path, OP_STREAM_ACCESS | OP_NO_RESOLVE_LINK)
+}
+
+/// Checks whether the file at `path` can be opened with the access modes in `access_mode` (`ACCESS_READ`, `ACCESS_WRITE` or `ACCESS_EXECUTE`)
+pub fn access(path: &str, access_mode: u32) -> bool {
+    let op_mode = match metadata(path) {
+        Ok(meta) if meta.is_dir() => OP_DIRECTORY_ACCESS,
+        Ok(_) => OP_STREAM_ACCESS,
+        Err(_) => return false,
+    };
+    match open_file(HandlePtr::null(), path, access_mode, op_mode) {
+        Ok(hdl) => {
+            unsafe {
+                CloseFile(hdl);
+            }
+            true
+        }
+        Err(_) => false,
+    }
 }
 
 #[macro_export]
@@ -43,7 +515,10 @@ macro_rules! print{
     ($($tt:tt)*) => {
         {
             use core::fmt::Write as _;
-            ::core::write!($crate::io::stdout(), $($tt)*).unwrap()
+            $crate::io::with_stdout(|out| {
+                // A failed write is kept in the writer, to be reported by the command that made it
+                let _ = ::core::write!(out, $($tt)*);
+            })
         }
     };
 }
@@ -53,7 +528,9 @@ macro_rules! eprint{
     ($($tt:tt)*) => {
         {
             use core::fmt::Write as _;
-            ::core::write!($crate::io::stderr(), $($tt)*).unwrap()
+            $crate::io::with_stderr(|out| {
+                let _ = ::core::write!(out, $($tt)*);
+            })
         }
     };
 }
@@ -63,7 +540,9 @@ macro_rules! println{
     ($($tt:tt)*) => {
         {
             use core::fmt::Write as _;
-            ::core::writeln!($crate::io::stdout(), $($tt)*).unwrap()
+            $crate::io::with_stdout(|out| {
+                let _ = ::core::writeln!(out, $($tt)*);
+            })
         }
     };
 }
@@ -73,7 +552,9 @@ macro_rules! eprintln{
     ($($tt:tt)*) => {
         {
             use core::fmt::Write as _;
-            ::core::writeln!($crate::io::stderr(), $($tt)*).unwrap()
+            $crate::io::with_stderr(|out| {
+                let _ = ::core::writeln!(out, $($tt)*);
+            })
         }
     };
 }
@@ -110,25 +591,79 @@ impl Write for Stdio {
         Ok(())
     }
 
+    /// Writes all of `buf`, continuing after a partial write or one that was interrupted by a signal
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf) {
+                Ok(0) => {
+                    return Err(Error::new_with_message(
+                        ErrorKind::WriteZero,
+                        "failed to write the whole buffer",
+                    ));
+                }
+                Ok(n) => buf = &buf[n..],
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
     fn size_hint(&mut self, _: usize) {
         // Cannot be implemented for `Stdio` efficiently. Only `FileHandle` has any manner of support for this
     }
 }
 
-pub struct BufReader<R> {
+/// The size of the buffer of a [`BufReader`] made with [`BufReader::new`]
+pub const DEFAULT_BUF_SIZE: usize = 64;
+
+/// The storage for the buffer of a [`BufReader`], which is either a fixed array or a `Vec` that can grow
+pub trait Buffer: AsRef<[u8]> + AsMut<[u8]> {
+    /// Makes the buffer bigger, returning `false` if it can't grow
+    fn grow(&mut self) -> bool {
+        false
+    }
+}
+
+impl<const N: usize> Buffer for [u8; N] {}
+
+impl Buffer for Vec<u8> {
+    fn grow(&mut self) -> bool {
+        let len = self.len().max(DEFAULT_BUF_SIZE);
+        self.resize(len * 2, 0);
+        true
+    }
+}
+
+pub struct BufReader<R, B = [u8; DEFAULT_BUF_SIZE]> {
     underlying: R,
     buf_pos: usize,
     buf_len: usize,
-    buf: [u8; 64],
+    buf: B,
 }
 
 impl<R> BufReader<R> {
     pub const fn new(inner: R) -> Self {
+        Self::with_buffer(inner, [0; DEFAULT_BUF_SIZE])
+    }
+}
+
+impl<R> BufReader<R, Vec<u8>> {
+    /// Makes a reader with a buffer of `capacity` bytes on the heap, which [`fill_progress`][BufReadProgress::fill_progress] grows
+    /// when a line doesn't fit
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self::with_buffer(inner, vec![0; capacity])
+    }
+}
+
+impl<R, B> BufReader<R, B> {
+    /// Makes a reader that uses `buf` as its buffer, such as an array of a different size than [`DEFAULT_BUF_SIZE`]
+    pub const fn with_buffer(inner: R, buf: B) -> Self {
         Self {
             underlying: inner,
             buf_pos: 0,
             buf_len: 0,
-            buf: [0; 64],
+            buf,
         }
     }
 
@@ -137,14 +672,14 @@ impl<R> BufReader<R> {
     }
 }
 
-impl<R: Read> BufRead for BufReader<R> {
+impl<R: Read, B: Buffer> BufRead for BufReader<R, B> {
     fn fill_buf(&mut self) -> core::result::Result<&[u8], Self::ReadError> {
         if self.buf_pos >= self.buf_len {
-            self.buf_len = self.underlying.read(&mut self.buf)?;
+            self.buf_len = self.underlying.read(self.buf.as_mut())?;
             self.buf_pos = 0;
         }
 
-        Ok(&self.buf[self.buf_pos..self.buf_len])
+        Ok(&self.buf.as_ref()[self.buf_pos..self.buf_len])
     }
 
     fn consume(&mut self, amount: usize) {
@@ -152,7 +687,38 @@ impl<R: Read> BufRead for BufReader<R> {
     }
 }
 
-impl<R: Read> Read for BufReader<R> {
+impl<R: Read, B: Buffer> BufReadProgress for BufReader<R, B> {
+    type BufReadError = Error;
+
+    /// Reads more input after what is already buffered, growing the buffer if it is full,
+    /// so that a caller can see a whole line at once however long it is
+    fn fill_progress(
+        &mut self,
+    ) -> core::result::Result<&[u8], BufError<Self::BufReadError, Self::ReadError>> {
+        // Move the unread input to the start, to make room after it
+        self.buf.as_mut().copy_within(self.buf_pos..self.buf_len, 0);
+        self.buf_len -= self.buf_pos;
+        self.buf_pos = 0;
+
+        if self.buf_len == self.buf.as_ref().len() && !self.buf.grow() {
+            return Err(BufError::BufferErr(Error::new_with_message(
+                ErrorKind::OutOfMemory,
+                "the buffer is full",
+            )));
+        }
+        let n = self
+            .underlying
+            .read(&mut self.buf.as_mut()[self.buf_len..])
+            .map_err(BufError::OtherErr)?;
+        if n == 0 {
+            return Err(BufError::End);
+        }
+        self.buf_len += n;
+        Ok(&self.buf.as_ref()[..self.buf_len])
+    }
+}
+
+impl<R: Read, B: Buffer> Read for BufReader<R, B> {
     type ReadError = R::ReadError;
     fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::ReadError> {
         let inner = self.fill_buf()?;
@@ -163,6 +729,148 @@ impl<R: Read> Read for BufReader<R> {
     }
 }
 
+/// A reader that reads one byte at a time, so that it never takes more input from the underlying reader than has been consumed.
+///
+/// This lets a builtin like `read` stop at a delimiter and leave the rest of the input to the commands that follow.
+pub struct ByteReader<R> {
+    underlying: R,
+    byte: [u8; 1],
+    filled: bool,
+}
+
+impl<R> ByteReader<R> {
+    pub const fn new(inner: R) -> Self {
+        Self {
+            underlying: inner,
+            byte: [0],
+            filled: false,
+        }
+    }
+}
+
+impl<R: Read> BufRead for ByteReader<R> {
+    fn fill_buf(&mut self) -> core::result::Result<&[u8], Self::ReadError> {
+        if !self.filled {
+            self.filled = self.underlying.read(&mut self.byte)? == 1;
+        }
+
+        Ok(&self.byte[..self.filled as usize])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        if amount > 0 {
+            self.filled = false;
+        }
+    }
+}
+
+impl<R: Read> Read for ByteReader<R> {
+    type ReadError = R::ReadError;
+    fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::ReadError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let inner = self.fill_buf()?;
+        let len = inner.len();
+        buf[..len].copy_from_slice(inner);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+/// The size of the buffer of a [`BufWriter`] made with [`BufWriter::new`]
+pub const DEFAULT_WRITE_BUF_SIZE: usize = 1024;
+
+/// A writer that collects small writes, such as the pieces of a formatted line, and passes them on together.
+///
+/// Buffered output is written when the buffer fills, on [`flush`][Write::flush], and when the writer is dropped.
+/// A line-buffered writer, as for a terminal, also writes each line as soon as it is complete.
+pub struct BufWriter<W: Write> {
+    underlying: W,
+    buf: Vec<u8>,
+    capacity: usize,
+    line_buffered: bool,
+    /// The first error from a write through [`core::fmt::Write`], which can't return it
+    error: Option<W::WriteError>,
+}
+
+impl<W: Write> BufWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_WRITE_BUF_SIZE, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            underlying: inner,
+            buf: Vec::with_capacity(capacity),
+            capacity,
+            line_buffered: false,
+            error: None,
+        }
+    }
+
+    /// Takes the first error from formatting into the writer, such as with [`write!`], since the last call
+    pub fn take_error(&mut self) -> Option<W::WriteError> {
+        self.error.take()
+    }
+
+    /// Writes out the first `len` bytes of the buffer.
+    ///
+    /// They are removed from the buffer even if writing fails, so that a closed stream doesn't fill it up.
+    fn write_out(&mut self, len: usize) -> core::result::Result<(), W::WriteError> {
+        let res = self.underlying.write_all(&self.buf[..len]);
+        self.buf.drain(..len);
+        res
+    }
+}
+
+impl<W: Write<WriteError = E, FlushError = E>, E> Write for BufWriter<W> {
+    type WriteError = E;
+    type FlushError = E;
+
+    fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, E> {
+        if self.buf.len() + buf.len() > self.capacity {
+            self.write_out(self.buf.len())?;
+        }
+        // Writes that wouldn't fit in the buffer anyway are passed straight on
+        if buf.len() >= self.capacity {
+            return self.underlying.write_all(buf).map(|()| buf.len());
+        }
+
+        self.buf.extend_from_slice(buf);
+        if self.line_buffered
+            && let Some(n) = memrchr(b'\n', &self.buf)
+        {
+            self.write_out(n + 1)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> core::result::Result<(), E> {
+        self.write_out(self.buf.len())?;
+        self.underlying.flush()
+    }
+
+    fn size_hint(&mut self, bytes: usize) {
+        self.buf.reserve(bytes.min(self.capacity));
+    }
+}
+
+impl<W: Write<WriteError = E, FlushError = E>, E> core::fmt::Write for BufWriter<W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|e| {
+            self.error.get_or_insert(e);
+            core::fmt::Error
+        })
+    }
+}
+
+impl<W: Write> Drop for BufWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.write_out(self.buf.len());
+    }
+}
+
 mod ex;
 
-pub use ex::{BufReadEx, ReadToStringError};
+pub use ex::BufReadEx;
@@ -0,0 +1,66 @@
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use lilium_sys::sys::kstr::KStrCPtr;
+
+/// The character that would stand for the byte `0x00` in a string from [`decode_bytes`].
+///
+/// Bytes that aren't valid UTF-8 are always `0x80` or above, so they become the characters from `U+10FF80` to `U+10FFFF`,
+/// at the end of the last private use plane, where the shell's strings can hold them and write them back out unchanged.
+const BYTE_BASE: u32 = 0x10FF00;
+
+/// The byte that `c` stands for, if it was read as a byte that isn't valid UTF-8
+pub fn raw_byte(c: char) -> Option<u8> {
+    match c as u32 {
+        c @ 0x10FF80..=0x10FFFF => Some((c - BYTE_BASE) as u8),
+        _ => None,
+    }
+}
+
+/// The character that stands for the byte `b`, which is `0x80` or above
+fn byte_char(b: u8) -> char {
+    char::from_u32(BYTE_BASE + b as u32).unwrap()
+}
+
+/// Decodes `bytes` as UTF-8, keeping the bytes that aren't valid as characters that [`encode_bytes`] turns back into them.
+///
+/// Characters that were really in the input but fall in the range used for bytes are kept as the bytes of their UTF-8 encoding,
+/// so that every input comes back out of [`encode_bytes`] unchanged.
+pub fn decode_bytes(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if raw_byte(c).is_some() {
+                out.extend(c.encode_utf8(&mut [0; 4]).bytes().map(byte_char));
+            } else {
+                out.push(c);
+            }
+        }
+        out.extend(chunk.invalid().iter().copied().map(byte_char));
+    }
+    out
+}
+
+/// The bytes that `s` stands for, where the characters from [`decode_bytes`] become the bytes they were read as
+pub fn encode_bytes(s: &str) -> Cow<'_, [u8]> {
+    if !s.chars().any(|c| raw_byte(c).is_some()) {
+        return Cow::Borrowed(s.as_bytes());
+    }
+
+    let mut out = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        match raw_byte(c) {
+            Some(b) => out.push(b),
+            None => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Points a kernel string at `bytes`, which must outlive every use of it.
+///
+/// Unlike [`KStrCPtr::from_str`], this can pass names that aren't valid UTF-8, such as from [`encode_bytes`].
+pub fn kstr(bytes: &[u8]) -> KStrCPtr {
+    KStrCPtr {
+        str_ptr: bytes.as_ptr().cast(),
+        len: bytes.len(),
+    }
+}
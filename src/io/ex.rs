@@ -8,8 +8,37 @@ pub enum ReadToStringError<R> {
 }
 
 pub trait BufReadEx: BufRead {
+    /// Reads up to and including the delimiter `b`, or to the end of the input, appending to `buf`.
+    ///
+    /// Returns the number of bytes read, which is 0 only at the end of the input.
     fn read_until(&mut self, buf: &mut Vec<u8>, b: u8) -> Result<usize, Self::ReadError>;
+    /// Reads a line, including its `\n`, appending it to `st`
     fn read_line(&mut self, st: &mut String) -> Result<usize, ReadToStringError<Self::ReadError>>;
+    /// Reads to the end of the input, appending to `buf`
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize, Self::ReadError>;
+    /// Reads to the end of the input, appending to `st`
+    fn read_to_string(
+        &mut self,
+        st: &mut String,
+    ) -> Result<usize, ReadToStringError<Self::ReadError>>;
+    /// Skips past the next delimiter `b`, returning how many bytes were skipped, including the delimiter
+    fn skip_until(&mut self, b: u8) -> Result<usize, Self::ReadError>;
+
+    /// An iterator over the lines of the input, without their `\n` or `\r\n`
+    fn lines(self) -> Lines<Self>
+    where
+        Self: Sized,
+    {
+        Lines(self)
+    }
+
+    /// An iterator over the parts of the input that are separated by `b`, without the delimiter
+    fn split(self, b: u8) -> Split<Self>
+    where
+        Self: Sized,
+    {
+        Split(self, b)
+    }
 }
 
 impl<R: BufRead> BufReadEx for R {
@@ -18,14 +47,14 @@ impl<R: BufRead> BufReadEx for R {
         loop {
             let inner_buf = self.fill_buf()?;
 
-            if inner_buf.len() == 0 {
+            if inner_buf.is_empty() {
                 return Ok(total_len);
             }
 
             match memchr(b, inner_buf) {
                 Some(n) => {
                     let n = n + 1;
-                    total_len += n + 1;
+                    total_len += n;
                     buf.extend_from_slice(&inner_buf[..n]);
                     self.consume(n);
                     break Ok(total_len);
@@ -42,6 +71,96 @@ impl<R: BufRead> BufReadEx for R {
     fn read_line(&mut self, st: &mut String) -> Result<usize, ReadToStringError<Self::ReadError>> {
         unsafe { append_to_string(st, |buf| self.read_until(buf, b'\n')) }
     }
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize, Self::ReadError> {
+        let mut total_len = 0;
+        loop {
+            let inner_buf = self.fill_buf()?;
+
+            if inner_buf.is_empty() {
+                return Ok(total_len);
+            }
+
+            let n = inner_buf.len();
+            total_len += n;
+            buf.extend_from_slice(inner_buf);
+            self.consume(n);
+        }
+    }
+    fn read_to_string(
+        &mut self,
+        st: &mut String,
+    ) -> Result<usize, ReadToStringError<Self::ReadError>> {
+        unsafe { append_to_string(st, |buf| BufReadEx::read_to_end(self, buf)) }
+    }
+    fn skip_until(&mut self, b: u8) -> Result<usize, Self::ReadError> {
+        let mut total_len = 0;
+        loop {
+            let inner_buf = self.fill_buf()?;
+
+            if inner_buf.is_empty() {
+                return Ok(total_len);
+            }
+
+            match memchr(b, inner_buf) {
+                Some(n) => {
+                    let n = n + 1;
+                    total_len += n;
+                    self.consume(n);
+                    break Ok(total_len);
+                }
+                None => {
+                    let n = inner_buf.len();
+                    total_len += n;
+                    self.consume(n);
+                }
+            }
+        }
+    }
+}
+
+/// The iterator returned by [`BufReadEx::lines`]
+pub struct Lines<R>(R);
+
+impl<R: BufRead> Iterator for Lines<R> {
+    type Item = Result<String, ReadToStringError<R::ReadError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        match self.0.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                Some(Ok(line))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// The iterator returned by [`BufReadEx::split`]
+pub struct Split<R>(R, u8);
+
+impl<R: BufRead> Iterator for Split<R> {
+    type Item = Result<Vec<u8>, R::ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = Vec::new();
+        match self.0.read_until(&mut buf, self.1) {
+            Ok(0) => None,
+            Ok(_) => {
+                if buf.last() == Some(&self.1) {
+                    buf.pop();
+                }
+                Some(Ok(buf))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
 }
 
 // Taken from std::io impl
@@ -14,54 +14,149 @@ mod shell;
 mod start;
 mod system;
 
-use core::panic::PanicInfo;
+use core::{cell::Cell, panic::PanicInfo};
 
-use alloc::string::String;
-use error_repr::Error;
-use io::{BufReadEx, BufReader, ReadToStringError, stderr, stdin};
+use alloc::{string::String, vec::Vec};
+use genio::bufio::BufRead;
+use io::{BufReadEx, BufReader, ByteReader, stderr, stdin};
 use lilium_sys::{
     sys::except::{ExceptionStatusInfo, UnmanagedException},
     uuid::parse_uuid,
 };
-use shell::{parse_shell, split_shell};
-
-use crate::shell::exec_line;
+use shell::{exec_list, expand_aliases, parse_shell, split_shell, vars};
 
 fn main() -> io::Result<i32> {
+    if let Err(e) = shell::install_handler() {
+        eprintln!("minish: cannot handle interrupts: {e}");
+    }
+    let status = match start::args().nth(1) {
+        Some(path) => io::File::open(&path)
+            .and_then(|file| run(BufReader::with_capacity(SCRIPT_BUF_SIZE, file), Some(&path))),
+        // Commands from stdin are read a byte at a time, so that the programs they run get the input after them,
+        // as when a script is piped in
+        None => run(ByteReader::new(stdin()), None),
+    };
+    shell::run_exit_trap();
+    status
+}
+
+/// How much of a script is read at once, since nothing else reads from it
+const SCRIPT_BUF_SIZE: usize = 4096;
+
+/// How many times in a row reading input can fail before an interactive shell gives up
+const MAX_READ_ERRORS: u32 = 16;
+
+/// Reads and executes commands until the end of input.
+///
+/// `script` is the path of the script being run, or `None` for an interactive shell.
+/// A script stops at the first input that can't be read, but an interactive shell reports it and drops the line,
+/// since there may be no other shell to go back to.
+fn run<R: BufRead<ReadError = io::Error>>(mut reader: R, script: Option<&str>) -> io::Result<i32> {
     let mut line = String::new();
-    let mut reader = BufReader::new(stdin());
-    loop {
+    let mut bytes = Vec::new();
+    let mut lineno = 0;
+    let mut errors = 0;
+    'line: loop {
         line.clear();
-        print!("# ");
-        let n = reader.read_line(&mut line).map_err(|e| match e {
-            ReadToStringError::Read(r) => r,
-            ReadToStringError::InvalidUtf8 => {
-                Error::new_with_message(io::ErrorKind::InvalidData, "Invalid UTF-8 Text")
+        if script.is_none() {
+            print!("# ");
+        }
+
+        let first_line = lineno + 1;
+        loop {
+            io::flush_output();
+            bytes.clear();
+            let n = match reader.read_until(&mut bytes, b'\n') {
+                Ok(n) => n,
+                // A signal arrived while waiting for input, where Ctrl-C discards the line and anything else reads on
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                    shell::run_pending_traps();
+                    if shell::take_interrupt() {
+                        if script.is_some() {
+                            return Ok(shell::INTERRUPT_STATUS);
+                        }
+                        println!();
+                        continue 'line;
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    errors += 1;
+                    if script.is_some() || errors >= MAX_READ_ERRORS {
+                        return Err(e);
+                    }
+                    eprintln!("minish: {e}");
+                    continue 'line;
+                }
+            };
+            errors = 0;
+            if n == 0 {
+                break;
+            }
+            // Bytes that aren't valid UTF-8, such as in file names, are kept as the characters that stand for them
+            line.push_str(&io::decode_bytes(&bytes));
+            lineno += 1;
+
+            // Keep reading while the input ends inside a quote or compound command
+            match parse_shell(expand_aliases(split_shell(&line))) {
+                Err(e) if e.is_incomplete() => {
+                    if script.is_none() {
+                        print!("{}", vars::get("PS2").as_deref().unwrap_or("> "))
+                    }
+                }
+                _ => break,
             }
-        })?;
-        if n == 0 {
-            println!("exit");
-            return Ok(0);
         }
 
-        let line = parse_shell(split_shell(&line));
+        if line.is_empty() {
+            if script.is_none() {
+                println!("exit");
+            }
+            return Ok(shell::last_status());
+        }
 
-        if let Some(cmd) = &line.command {
-            eprintln!("{line}");
-            match exec_line(&line) {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("Error spawning {cmd}: {e}")
+        let list = match parse_shell(expand_aliases(split_shell(&line))) {
+            Ok(list) => list,
+            Err(e) => {
+                match script {
+                    Some(path) => eprintln!(
+                        "{path}:{}:{}: {e}",
+                        first_line + e.span.line - 1,
+                        e.span.col
+                    ),
+                    None => eprintln!("minish: {e}"),
                 }
+                eprint!("{}", e.snippet(&line));
+                if script.is_some() {
+                    return Ok(2);
+                }
+                continue;
+            }
+        };
+
+        exec_list(&list);
+        if shell::take_interrupt() {
+            // A script stops at Ctrl-C, but an interactive shell goes back to the prompt
+            if script.is_some() {
+                return Ok(shell::INTERRUPT_STATUS);
             }
+            println!();
         }
     }
 }
 
+/// Set once the shell has panicked, so that a panic in the `EXIT` trap doesn't run it again
+#[thread_local]
+static PANICKING: Cell<bool> = Cell::new(false);
+
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     use core::fmt::Write;
+    io::flush_output();
     let _ = writeln!(stderr(), "Panicked at {}", info.message());
+    if !PANICKING.replace(true) {
+        shell::run_exit_trap();
+    }
     unsafe {
         UnmanagedException(&ExceptionStatusInfo {
             except_code: parse_uuid("4c0c6658-59ae-5675-90c3-ffcc0a7219ad"),
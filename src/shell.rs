@@ -1,29 +1,68 @@
-use core::{cell::LazyCell, ops::Deref};
+use core::{cell::Cell, ops::Deref};
 
-use alloc::{borrow::Cow, string::String, vec::Vec};
+use alloc::{
+    borrow::Cow,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 use bytemuck::Zeroable;
 use lilium_sys::sys::{
-    fs::{ACCESS_READ, FileHandle, FileOpenOptions, OP_DIRECTORY_ACCESS, OpenFile},
-    handle::HandlePtr,
-    io::MODE_BLOCKING,
-    kstr::{KCSlice, KStrCPtr},
+    error::INTERRUPTED,
+    kstr::KCSlice,
     option::ExtendedOptionHead,
     process::{
-        CREATE_PROCESS_OPTION_ARGS, CreateProcess, CreateProcessOption, CreateProcessOptionArgs,
-        JoinProcess,
+        CREATE_PROCESS_OPTION_ARGS, CREATE_PROCESS_OPTION_ENV, CREATE_PROCESS_OPTION_INIT_HANDLES,
+        CreateProcessOption, CreateProcessOptionArgs, CreateProcessOptionEnv,
+        CreateProcessOptionInitHandles, InitHandle, JoinProcess, TerminateProcess,
     },
     thread::JoinStatus,
 };
 
 use crate::{
     eprintln, exit,
-    helpers::SplitOnceOwned,
     io::{self, Error},
     println,
 };
 
+mod alias;
+mod arith;
+mod brace;
+mod bracket;
+mod escape;
+mod parse;
+mod path;
+mod pattern;
+mod print;
+mod quote;
+mod read;
+mod redirect;
+mod regex;
+mod test;
+mod tilde;
+mod trap;
+pub mod vars;
+mod word;
+
+pub use parse::{
+    AndOr, Command, CondExpr, Connector, List, Op, Pipeline, Span, SyntaxError, SyntaxErrorKind,
+    Token, TokenKind, parse_shell,
+};
+pub use alias::expand_aliases;
+pub use quote::quote;
+pub use trap::{
+    INTERRUPT_STATUS, install_handler, run_exit_trap, run_pending_traps, take_interrupt,
+};
+pub use word::{Expansion, Word, WordPart};
+use word::WordBuilder;
+
 pub fn split_shell(x: &str) -> SplitShell {
-    SplitShell(x)
+    SplitShell {
+        input: x,
+        rest: x,
+        pos: (0, 1, 1),
+        failed: false,
+    }
 }
 
 enum State {
@@ -32,108 +71,326 @@ enum State {
     DQuote,
     EscapeDQuote,
     SQuote,
-    EscapeSQuote,
 }
 
-pub struct SplitShell<'a>(&'a str);
+/// Finds the `))` that closes an arithmetic expression, given the text following the opening `((`.
+///
+/// Returns the length of the expression.
+fn arith_len(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut chars = s.char_indices().peekable();
+    while let Some((n, c)) = chars.next() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            ')' => {
+                return match chars.peek() {
+                    Some((_, ')')) => Some(n),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses the expansion following a `$`, returning it and the length of the text it was parsed from
+fn dollar_expansion(s: &str) -> Option<(Expansion<'_>, usize)> {
+    if let Some(expr) = s.strip_prefix("((") {
+        let len = arith_len(expr)?;
+        return Some((Expansion::Arith(Cow::Borrowed(&expr[..len])), len + 4));
+    }
+
+    if let Some(braced) = s.strip_prefix('{') {
+        let len = braced.find('}')?;
+        let name = &braced[..len];
+        let valid = vars::is_valid_name(name)
+            || is_special_param(name)
+            || vars::split_subscript(name).is_some();
+        if !valid {
+            return None;
+        }
+        return Some((Expansion::Param(Cow::Borrowed(name)), len + 2));
+    }
+
+    let len = match s.chars().next()? {
+        c if c == '_' || c.is_ascii_alphabetic() => s
+            .find(|c: char| !(c == '_' || c.is_ascii_alphanumeric()))
+            .unwrap_or(s.len()),
+        c if is_special_param(&s[..c.len_utf8()]) => 1,
+        _ => return None,
+    };
+    Some((Expansion::Param(Cow::Borrowed(&s[..len])), len))
+}
+
+/// Special parameters, which are named by a single digit or punctuation character
+fn is_special_param(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(
+        (chars.next(), chars.next()),
+        (Some('0'..='9' | '?' | '$' | '#' | '!' | '@' | '*' | '-'), None)
+    )
+}
+
+pub struct SplitShell<'a> {
+    input: &'a str,
+    rest: &'a str,
+    /// A byte offset into the input, with its line and column, from which the position of the next token is counted
+    pos: (usize, usize, usize),
+    /// Set once an error was returned, which ends the token stream
+    failed: bool,
+}
+
+impl<'a> SplitShell<'a> {
+    /// Computes the span of `start..end` in the input, which must not be before any previously computed span
+    fn span(&mut self, start: usize, end: usize) -> Span {
+        let (offset, mut line, mut col) = self.pos;
+        for c in self.input[offset..start].chars() {
+            if c == '\n' {
+                line += 1;
+                col = 1;
+            } else {
+                col += 1;
+            }
+        }
+        self.pos = (start, line, col);
+        Span {
+            start,
+            end,
+            line,
+            col,
+        }
+    }
+
+    fn error(&mut self, kind: SyntaxErrorKind, start: usize, end: usize) -> SyntaxError {
+        self.failed = true;
+        self.rest = "";
+        SyntaxError {
+            kind,
+            span: self.span(start, end),
+        }
+    }
+
+    /// An error for a `\` that continues the line at the end of the input
+    fn continuation_error(&mut self) -> SyntaxError {
+        let at = self.input.rfind('\\').unwrap_or(self.input.len());
+        self.error(SyntaxErrorKind::Continuation, at, at + 1)
+    }
+}
 
 impl<'a> Iterator for SplitShell<'a> {
-    type Item = Cow<'a, str>;
+    type Item = Result<Token<'a>, SyntaxError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
         let mut state = State::Normal;
 
-        let s = self.0.trim();
+        let mut s = self.rest;
+        loop {
+            s = s.trim_start_matches(|c: char| c.is_whitespace() && c != '\n');
+            if let Some(rest) = s.strip_prefix("\\\n") {
+                // A line continuation between words is just whitespace
+                s = rest;
+                if s.trim_start_matches(|c: char| c.is_whitespace() && c != '\n').is_empty() {
+                    return Some(Err(self.continuation_error()));
+                }
+            } else if let Some(comment) = s.strip_prefix('#') {
+                s = comment.find('\n').map_or("", |n| &comment[n..]);
+            } else {
+                break;
+            }
+        }
+
         if s.is_empty() {
-            self.0 = s;
+            self.rest = s;
             return None;
         }
+        let start = self.input.len() - s.len();
 
-        let mut buf = String::new();
+        // `(( expr ))` is kept as a single word, so that it can be recognized as an arithmetic command
+        if let Some(len) = s.strip_prefix("((").and_then(arith_len) {
+            let end = len + 4;
+            self.rest = &s[end..];
+            return Some(Ok(Token {
+                kind: TokenKind::Word(Word::literal(Cow::Borrowed(&s[..end]))),
+                span: self.span(start, start + end),
+            }));
+        }
+
+        if let Some(op) = Op::parse(s) {
+            let len = op.as_str().len();
+            self.rest = &s[len..];
+            return Some(Ok(Token {
+                kind: TokenKind::Op(op),
+                span: self.span(start, start + len),
+            }));
+        }
+
+        let mut word = WordBuilder::new();
+        // Whether the word is exactly the source text, with no quotes, escapes or expansions
+        let mut plain = true;
+        let mut skip_to = 0;
+        let mut end = s.len();
+        let mut quote_start = 0;
+        // Whether the last thing in the input was a `\\` followed by a newline
+        let mut continued = false;
 
         for (n, c) in s.char_indices() {
+            if n < skip_to {
+                continue;
+            }
+            continued = false;
             match state {
+                State::Normal | State::DQuote
+                    if c == '$' && !(matches!(state, State::Normal) && s[n + 1..].starts_with('\'')) =>
+                {
+                    let quoted = matches!(state, State::DQuote);
+                    match dollar_expansion(&s[n + 1..]) {
+                        Some((expansion, len)) => {
+                            plain = false;
+                            word.push_part(WordPart::Expansion(expansion, quoted));
+                            skip_to = n + 1 + len;
+                        }
+                        None => word.push(c, quoted),
+                    }
+                }
                 State::Normal => match c {
-                    c if c.is_whitespace() => {
-                        self.0 = &s[n..];
-
-                        if !buf.is_empty() {
-                            return Some(Cow::Owned(buf));
-                        } else {
-                            return Some(Cow::Borrowed(&s[..n]));
+                    c if c.is_whitespace() || Op::parse(&s[n..]).is_some() => {
+                        end = n;
+                        break;
+                    }
+                    '$' if s[n + 1..].starts_with('\'') => {
+                        plain = false;
+                        word.mark_quoted();
+                        quote_start = n + 1;
+                        match escape::ansi_c_len(&s[n + 2..]) {
+                            Some(len) => {
+                                for c in escape::ansi_c(&s[n + 2..n + 2 + len]).chars() {
+                                    word.push(c, true);
+                                }
+                                skip_to = n + 2 + len + 1;
+                            }
+                            None => {
+                                // The rest of the input is inside the quote, and more is needed to finish it
+                                state = State::SQuote;
+                                skip_to = s.len();
+                            }
                         }
                     }
                     '\\' => {
-                        buf.push_str(&s[..n]);
+                        plain = false;
                         state = State::Escape;
                     }
                     '"' => {
-                        buf.push_str(&s[..n]);
+                        plain = false;
+                        word.mark_quoted();
+                        quote_start = n;
                         state = State::DQuote;
                     }
                     '\'' => {
-                        buf.push_str(&s[..n]);
+                        plain = false;
+                        word.mark_quoted();
+                        quote_start = n;
                         state = State::SQuote;
                     }
-                    ';' => {
-                        if n == 0 {
-                            self.0 = &s[1..];
-
-                            if !buf.is_empty() {
-                                return Some(Cow::Owned(buf));
-                            } else {
-                                return Some(Cow::Borrowed(&s[..1]));
-                            }
-                        } else {
-                            self.0 = &s[n..];
-                            if !buf.is_empty() {
-                                return Some(Cow::Owned(buf));
-                            } else {
-                                return Some(Cow::Borrowed(&s[..n]));
-                            }
-                        }
-                    }
-                    _ => continue,
+                    c => word.push(c, false),
                 },
                 State::Escape => {
-                    buf.push(c);
+                    // A backslash-newline is removed entirely, joining the two lines
+                    if c == '\n' {
+                        continued = true;
+                    } else {
+                        word.push(c, true);
+                    }
                     state = State::Normal;
                 }
                 State::EscapeDQuote => {
-                    buf.push(c);
+                    // Inside double quotes, a backslash only escapes characters that would otherwise be special
+                    match c {
+                        '\n' => continued = true,
+                        '$' | '`' | '"' | '\\' => word.push(c, true),
+                        c => {
+                            word.push('\\', true);
+                            word.push(c, true);
+                        }
+                    }
                     state = State::DQuote;
                 }
-                State::EscapeSQuote => {
-                    buf.push(c);
-                    state = State::SQuote;
-                }
                 State::DQuote => match c {
                     '"' => state = State::Normal,
                     '\\' => state = State::EscapeDQuote,
-                    _ => buf.push(c),
+                    _ => word.push(c, true),
                 },
                 State::SQuote => match c {
                     '\'' => state = State::Normal,
-                    '\\' => state = State::EscapeSQuote,
-                    _ => buf.push(c),
+                    _ => word.push(c, true),
                 },
             }
         }
-        self.0 = &self.0[self.0.len()..];
+        self.rest = &s[end..];
+
+        let kind = match state {
+            State::Normal if continued && end == s.len() => Some(SyntaxErrorKind::Continuation),
+            State::Normal => None,
+            State::Escape => Some(SyntaxErrorKind::Continuation),
+            State::DQuote | State::EscapeDQuote => Some(SyntaxErrorKind::UnterminatedQuote('"')),
+            State::SQuote => Some(SyntaxErrorKind::UnterminatedQuote('\'')),
+        };
+        match kind {
+            Some(SyntaxErrorKind::Continuation) => return Some(Err(self.continuation_error())),
+            Some(kind) => {
+                let end = self.input.len();
+                return Some(Err(self.error(kind, start + quote_start, end)));
+            }
+            None => {}
+        }
 
-        Some(Cow::Borrowed(s))
+        let span = self.span(start, start + end);
+        if plain {
+            return Some(Ok(Token {
+                kind: TokenKind::Word(Word::literal(Cow::Borrowed(&s[..end]))),
+                span,
+            }));
+        }
+
+        Some(Ok(Token {
+            kind: TokenKind::Word(word.finish()),
+            span,
+        }))
     }
 }
 
 pub struct EnvVar<'a> {
     pub key: Cow<'a, str>,
-    pub val: Cow<'a, str>,
+    pub val: Word<'a>,
+}
+
+/// A redirection of one of the shell's descriptors, like `2>file` or `3>&-`
+pub struct Redirect<'a> {
+    pub fd: u32,
+    pub op: Op,
+    /// The file, or for `>&`, the descriptor to copy or `-` to close it
+    pub target: Word<'a>,
+}
+
+impl<'a> core::fmt::Display for Redirect<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.fd != self.op.default_fd() {
+            write!(f, "{}", self.fd)?;
+        }
+        f.write_str(self.op.as_str())?;
+        self.target.fmt(f)
+    }
 }
 
 pub struct ShellLine<'a> {
     pub env: Vec<EnvVar<'a>>,
-    pub command: Option<Cow<'a, str>>,
-    pub args: Vec<Cow<'a, str>>,
+    pub command: Option<Word<'a>>,
+    pub args: Vec<Word<'a>>,
+    pub redirects: Vec<Redirect<'a>>,
 }
 
 impl<'a> core::fmt::Display for ShellLine<'a> {
@@ -144,145 +401,445 @@ impl<'a> core::fmt::Display for ShellLine<'a> {
             sep = " ";
             f.write_str(&v.key)?;
             f.write_str("=")?;
-            f.write_str(&v.val)?;
+            v.val.fmt(f)?;
         }
 
         if let Some(cmd) = &self.command {
             f.write_str(sep)?;
-            f.write_str(cmd)?;
+            sep = " ";
+            cmd.fmt(f)?;
         }
 
         for a in &self.args {
             f.write_str(" ")?;
-            f.write_str(a)?;
+            a.fmt(f)?;
+        }
+
+        for r in &self.redirects {
+            f.write_str(sep)?;
+            sep = " ";
+            r.fmt(f)?;
         }
         Ok(())
     }
 }
 
-pub fn parse_shell<'a, I: Iterator<Item = Cow<'a, str>>>(mut iter: I) -> ShellLine<'a> {
-    let mut line = ShellLine {
-        env: Vec::new(),
-        command: None,
-        args: Vec::new(),
-    };
-    for c in &mut iter {
-        match c.split_once_owned("=") {
-            Ok((k, v)) => line.env.push(EnvVar { key: k, val: v }),
-            Err(e) => {
-                line.command = Some(e);
-                break;
+#[thread_local]
+static LAST_STATUS: Cell<i32> = Cell::new(0);
+
+/// The exit status of the last command that was run, which is the value of `$?`
+pub fn last_status() -> i32 {
+    LAST_STATUS.get()
+}
+
+#[thread_local]
+static XTRACE: Cell<bool> = Cell::new(false);
+
+/// Whether `set -x` is on, so that each command is printed before it is run
+pub fn xtrace() -> bool {
+    XTRACE.get()
+}
+
+/// Prints the words of a command being run for `set -x`, after the `PS4` prompt
+fn trace<S: AsRef<str>>(words: &[S]) {
+    let mut line = vars::get("PS4").unwrap_or_else(|| String::from("+ "));
+    for (n, word) in words.iter().enumerate() {
+        if n > 0 {
+            line.push(' ');
+        }
+        line.push_str(word.as_ref());
+    }
+    eprintln!("{line}");
+}
+
+/// Converts the status that a process was joined with into a shell exit status
+fn exit_status(status: &JoinStatus) -> i32 {
+    status.status_code as i32
+}
+
+/// Runs each command in `list`, returning the status of the last one
+pub fn exec_list(list: &List) -> i32 {
+    let mut status = 0;
+    for and_or in &list.0 {
+        if trap::interrupted() {
+            break;
+        }
+        status = exec_and_or(and_or);
+        trap::run_pending_traps();
+    }
+    status
+}
+
+fn exec_and_or(and_or: &AndOr) -> i32 {
+    let mut status = exec_pipeline(&and_or.first);
+    let mut ran = &and_or.first;
+    for (connector, pipeline) in &and_or.rest {
+        let run = match connector {
+            Connector::And => status == 0,
+            Connector::Or => status != 0,
+        };
+        if run {
+            status = exec_pipeline(pipeline);
+            ran = pipeline;
+        }
+    }
+
+    // Only a failure of the last pipeline runs the `ERR` trap, since the others are tested by `&&` or `||`
+    let last = and_or.rest.last().map_or(&and_or.first, |(_, pipeline)| pipeline);
+    if core::ptr::eq(ran, last)
+        && !ran.negated
+        && matches!(ran.command, Command::Simple(_) | Command::Cond(_))
+    {
+        trap::err(status);
+    }
+    status
+}
+
+fn exec_pipeline(pipeline: &Pipeline) -> i32 {
+    let mut status = exec_command(&pipeline.command);
+    if pipeline.negated {
+        status = (status == 0) as i32;
+    }
+    LAST_STATUS.set(status);
+    status
+}
+
+fn exec_command(command: &Command) -> i32 {
+    match command {
+        Command::Simple(line) => {
+            trap::debug();
+            match exec_line(line) {
+                Ok(status) => status,
+                Err(e) => {
+                    match &line.command {
+                        Some(cmd) => eprintln!("Error spawning {cmd}: {e}"),
+                        None => eprintln!("Error: {e}"),
+                    }
+                    if e.kind() == io::ErrorKind::NotFound {
+                        127
+                    } else {
+                        1
+                    }
+                }
+            }
+        }
+        Command::Group(list) => exec_list(list),
+        Command::If {
+            branches,
+            otherwise,
+        } => {
+            for (cond, body) in branches {
+                if trap::condition_scope(|| exec_list(cond)) == 0 {
+                    return exec_list(body);
+                }
+            }
+            otherwise.as_ref().map_or(0, exec_list)
+        }
+        Command::Loop { until, cond, body } => {
+            let mut status = 0;
+            while !trap::interrupted()
+                && (trap::condition_scope(|| exec_list(cond)) == 0) != *until
+            {
+                status = exec_list(body);
+            }
+            status
+        }
+        Command::For { var, words, body } => {
+            let values = match words {
+                Some(words) => {
+                    let mut values = Vec::new();
+                    for mut word in words.iter().flat_map(brace::expand_braces) {
+                        if let Err(e) = tilde::expand_tilde(&mut word, false)
+                            .and_then(|()| word.expand_fields(&mut values))
+                        {
+                            eprintln!("Error: {e}");
+                            return 1;
+                        }
+                    }
+                    values
+                }
+                None => crate::start::args().skip(2).map(Into::into).collect(),
+            };
+
+            let mut status = 0;
+            for val in values {
+                if trap::interrupted() {
+                    break;
+                }
+                vars::set(var, val);
+                status = exec_list(body);
+            }
+            status
+        }
+        Command::Cond(expr) => {
+            trap::debug();
+            match test::eval_cond(expr) {
+                Ok(val) => !val as i32,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    2
+                }
+            }
+        }
+    }
+}
+
+pub fn exec_line(line: &ShellLine) -> io::Result<i32> {
+    let Some(command) = &line.command else {
+        for var in &line.env {
+            let mut val = var.val.clone();
+            tilde::expand_tilde(&mut val, true)?;
+            let val = val.expand()?;
+            if xtrace() {
+                trace(&[format!("{}={}", var.key, quote(&val))]);
             }
+            vars::set(&var.key, val);
+        }
+        // Redirections without a command are still performed, such as to create a file, but only last for the line
+        redirect::apply(&line.redirects)?.restore();
+        return Ok(0);
+    };
+
+    if let Some(expr) = command.arith_command() {
+        if xtrace() {
+            trace(&[command.to_string()]);
         }
+        let val =
+            arith::eval(expr).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        return Ok((val == 0) as i32);
     }
 
-    line.args.extend(iter);
+    // Assignments before a command only go to its environment, rather than setting the shell's variables
+    let mut env = Vec::new();
+    for var in &line.env {
+        let mut val = var.val.clone();
+        tilde::expand_tilde(&mut val, true)?;
+        env.push((String::from(&*var.key), val.expand()?));
+    }
 
-    line
+    let mut argv = Vec::new();
+    for mut word in core::iter::once(command)
+        .chain(&line.args)
+        .flat_map(brace::expand_braces)
+    {
+        tilde::expand_tilde(&mut word, false)?;
+        word.expand_fields(&mut argv)?;
+    }
+    if argv.is_empty() {
+        redirect::apply(&line.redirects)?.restore();
+        return Ok(0);
+    }
+    if xtrace() {
+        let words = env
+            .iter()
+            .map(|(key, val)| format!("{key}={}", quote(val)))
+            .chain(argv.iter().map(|arg| quote(arg).into_owned()))
+            .collect::<Vec<_>>();
+        trace(&words);
+    }
+
+    // `exec` without a command applies its redirections to the shell itself, so they last for all later commands
+    if argv == ["exec"] {
+        redirect::apply(&line.redirects)?;
+        return Ok(0);
+    }
+    let saved = redirect::apply(&line.redirects)?;
+    // Only errors from writing this command's output are its own
+    io::take_stdout_error();
+    let status = run(&argv, &env).and_then(|status| {
+        // A builtin whose output couldn't be written, such as to a closed pipe, fails
+        io::flush_output();
+        io::take_stdout_error().map_or(Ok(status), Err)
+    });
+    saved.restore();
+    status
 }
 
-#[thread_local]
-static PATH: LazyCell<Vec<(&'static str, HandlePtr<FileHandle>)>> = LazyCell::new(|| {
-    let v = crate::start::var("PATH")
-        .into_iter()
-        .flat_map(|v| v.split(':'))
-        .filter_map(|v| {
-            let mut hdl = HandlePtr::null();
-            lilium_sys::result::Error::from_code(unsafe {
-                OpenFile(
-                    &mut hdl,
-                    HandlePtr::null(),
-                    KStrCPtr::from_str(v),
-                    &FileOpenOptions {
-                        stream_override: KStrCPtr::empty(),
-                        access_mode: ACCESS_READ,
-                        op_mode: OP_DIRECTORY_ACCESS,
-                        create_acl: HandlePtr::null(),
-                        blocking_mode: MODE_BLOCKING,
-                        extended_options: KCSlice::empty(),
-                    },
-                )
-            })
-            .ok()
-            .map(|_| (v, hdl))
-        })
-        .collect();
-    v
-});
+/// The names of the builtin commands, which are run by the shell itself rather than looked up in `PATH`
+pub(crate) const BUILTINS: [&str; 18] = [
+    "return", "exit", "logout", "let", "echo", "printf", "read", "test", "[", "set", "hash",
+    "type", "command", "which", "exec", "alias", "unalias", "trap",
+];
 
-pub fn exec_line(line: &ShellLine) -> io::Result<Option<JoinStatus>> {
-    match line.command.as_deref() {
-        Some(c @ ("return" | "exit" | "logout")) => {
+/// Runs an expanded command, which is either a builtin or a program.
+///
+/// `env` holds the assignments given before the command, which are added to a program's environment.
+fn run(argv: &[String], env: &[(String, String)]) -> io::Result<i32> {
+    match &*argv[0] {
+        c @ ("return" | "exit" | "logout") => {
             println!("exit command: {c}");
-            let status = if let Some(status) = line.args.get(0).map(|v| &**v) {
+            let status = if let Some(status) = argv.get(1) {
                 let val = status
                     .parse()
                     .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
                 val
             } else {
-                0
+                last_status()
             };
-            exit(status)
+            trap::exit(status)
         }
-        Some(n) => {
-            let mut hdl = HandlePtr::null();
-            let args = line
-                .command
-                .iter()
-                .chain(line.args.iter())
-                .map(Deref::deref)
-                .map(KStrCPtr::from_str)
-                .collect::<Vec<_>>();
-
-            let opts = [CreateProcessOption {
-                args: CreateProcessOptionArgs {
-                    header: ExtendedOptionHead {
-                        ty: CREATE_PROCESS_OPTION_ARGS,
-                        ..ExtendedOptionHead::ZERO
-                    },
-                    arguments: KCSlice::from_slice(&args),
-                },
-            }];
-            if !n.contains('/') {
-                'a: {
-                    let mut res = lilium_sys::sys::error::DOES_NOT_EXIST;
-                    for (path, path_ent) in PATH.iter().copied() {
-                        res = unsafe {
-                            CreateProcess(
-                                &mut hdl,
-                                path_ent,
-                                &KStrCPtr::from_str(n),
-                                &KCSlice::from_slice(&opts),
-                            )
-                        };
-                        if res >= 0 {
-                            break 'a;
-                        }
+        "let" => {
+            let mut val = 0;
+            for expr in &argv[1..] {
+                val = arith::eval(expr)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            }
+            Ok((val == 0) as i32)
+        }
+        "echo" => print::echo(&argv[1..]),
+        "printf" => print::printf(&argv[1..]),
+        "read" => read::read(&argv[1..]),
+        "test" => test::test(&argv[1..], false),
+        "[" => test::test(&argv[1..], true),
+        "set" => {
+            if argv.len() == 1 {
+                vars::for_each(|name, value| match value {
+                    vars::Value::Scalar(value) => println!("{name}={}", quote(value)),
+                    vars::Value::Array(values) => {
+                        let values = values.iter().map(|value| quote(value)).collect::<Vec<_>>();
+                        println!("{name}=({})", values.join(" "));
                     }
-                    return Err(io::Error::from_raw_os_error(res));
-                }
-            } else {
-                let res = unsafe {
-                    CreateProcess(
-                        &mut hdl,
-                        HandlePtr::null(),
-                        &KStrCPtr::from_str(n),
-                        &KCSlice::from_slice(&opts),
-                    )
+                });
+                return Ok(0);
+            }
+
+            let mut args = argv[1..].iter().map(Deref::deref);
+            while let Some(arg) = args.next() {
+                let (on, opt) = match arg.split_at_checked(1) {
+                    Some(("-", opt)) => (true, opt),
+                    Some(("+", opt)) => (false, opt),
+                    _ => {
+                        return Err(io::Error::new_with_message(
+                            io::ErrorKind::InvalidInput,
+                            "set: expected an option",
+                        ));
+                    }
+                };
+                // `xtrace` is the only option so far
+                let xtrace = match opt {
+                    "x" => true,
+                    "o" => args.next() == Some("xtrace"),
+                    _ => false,
                 };
-                if res < 0 {
-                    return Err(io::Error::from_raw_os_error(res));
+                if !xtrace {
+                    return Err(io::Error::new_with_message(
+                        io::ErrorKind::Unsupported,
+                        "set: unsupported option",
+                    ));
                 }
+                XTRACE.set(on);
             }
-            let mut status = bytemuck::zeroed();
-            let res = unsafe { JoinProcess(hdl, &mut status) };
-
-            if res < 0 {
-                Err(io::Error::from_raw_os_error(res))
+            Ok(0)
+        }
+        "alias" => alias::alias(&argv[1..]),
+        "unalias" => alias::unalias(&argv[1..]),
+        "trap" => trap::trap(&argv[1..]),
+        "hash" => path::hash(&argv[1..]),
+        "type" => path::type_(&argv[1..]),
+        "which" => path::which(&argv[1..]),
+        "command" => {
+            let args = match argv.get(1).map(Deref::deref) {
+                Some("-v") => return path::command_v(&argv[2..], false),
+                Some("-V") => return path::command_v(&argv[2..], true),
+                Some("--") => &argv[2..],
+                _ => &argv[1..],
+            };
+            // There are no shell functions to bypass yet, so this just runs the rest as a command
+            if args.is_empty() {
+                Ok(0)
             } else {
-                Ok(Some(status))
+                run(args, env)
+            }
+        }
+        "exec" if argv.len() > 1 => {
+            let status = run_program(&argv[1..], env)?;
+            io::flush_output();
+            exit(status)
+        }
+        "exec" => Ok(0),
+        _ => run_program(argv, env),
+    }
+}
+
+/// Runs a program, which gets the shell's open descriptors and environment, and waits for it to exit
+fn run_program(argv: &[String], env: &[(String, String)]) -> io::Result<i32> {
+    // Arguments are passed as the bytes they stand for, which may not be valid UTF-8
+    let args = argv
+        .iter()
+        .map(|arg| io::encode_bytes(arg))
+        .collect::<Vec<_>>();
+    let args = args.iter().map(|arg| io::kstr(arg)).collect::<Vec<_>>();
+    // The variables that the shell got from its environment are passed on, with their current values,
+    // unless an assignment before the command replaces them
+    let env = crate::start::vars()
+        .filter(|&(key, _)| !env.iter().any(|(name, _)| name == key))
+        .filter_map(|(key, _)| Some(format!("{key}={}", vars::get(key)?)))
+        .chain(env.iter().map(|(key, val)| format!("{key}={val}")))
+        .collect::<Vec<_>>();
+    let env = env
+        .iter()
+        .map(|var| io::encode_bytes(var))
+        .collect::<Vec<_>>();
+    let env = env.iter().map(|var| io::kstr(var)).collect::<Vec<_>>();
+    let handles = io::open_fds()
+        .into_iter()
+        .map(|(slot, hdl)| InitHandle {
+            slot,
+            hdl: hdl.cast(),
+        })
+        .collect::<Vec<_>>();
+
+    let opts = [
+        CreateProcessOption {
+            args: CreateProcessOptionArgs {
+                header: ExtendedOptionHead {
+                    ty: CREATE_PROCESS_OPTION_ARGS,
+                    ..ExtendedOptionHead::ZERO
+                },
+                arguments: KCSlice::from_slice(&args),
+            },
+        },
+        CreateProcessOption {
+            env: CreateProcessOptionEnv {
+                header: ExtendedOptionHead {
+                    ty: CREATE_PROCESS_OPTION_ENV,
+                    ..ExtendedOptionHead::ZERO
+                },
+                env: KCSlice::from_slice(&env),
+            },
+        },
+        CreateProcessOption {
+            init_handles: CreateProcessOptionInitHandles {
+                header: ExtendedOptionHead {
+                    ty: CREATE_PROCESS_OPTION_INIT_HANDLES,
+                    ..ExtendedOptionHead::ZERO
+                },
+                init_handles: KCSlice::from_slice(&handles),
+            },
+        },
+    ];
+    // The program writes to the same descriptors, so the shell's output has to come first
+    io::flush_output();
+    let hdl = path::spawn(&argv[0], &opts)?;
+    let mut status = bytemuck::zeroed();
+    let res = loop {
+        let res = unsafe { JoinProcess(hdl, &mut status) };
+        // Ctrl-C stops the program rather than the shell, unless `trap '' INT` ignores it,
+        // and other signals wait until it exits
+        if res == INTERRUPTED {
+            if trap::interrupt_pending() {
+                unsafe {
+                    TerminateProcess(hdl, trap::INTERRUPT_STATUS);
+                }
             }
+            continue;
         }
-        None => Ok(None),
+        break res;
+    };
+
+    if res < 0 {
+        Err(io::Error::from_raw_os_error(res))
+    } else {
+        Ok(exit_status(&status))
     }
 }
@@ -0,0 +1,258 @@
+use core::cell::{LazyCell, RefCell};
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::String,
+    vec::Vec,
+};
+
+use super::{
+    Op, Span, SyntaxError, SyntaxErrorKind, Token, TokenKind, Word, WordPart, parse::KEYWORDS,
+    quote, quote::single_quoted, split_shell, vars,
+};
+use crate::{eprintln, io, println};
+
+#[thread_local]
+static ALIASES: LazyCell<RefCell<BTreeMap<String, String>>> =
+    LazyCell::new(|| RefCell::new(BTreeMap::new()));
+
+/// Gets the value of the alias `name`
+pub fn get(name: &str) -> Option<String> {
+    ALIASES.borrow().get(name).cloned()
+}
+
+/// Whether `name` can be the name of an alias, which means it must be read as a single unquoted word
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| !c.is_whitespace() && !"/$`='\"\\;&|()<>".contains(c))
+}
+
+/// Prints an alias as the `alias` command that defines it
+pub fn print(name: &str, value: &str) {
+    println!("alias {}={}", quote(name), single_quoted(value));
+}
+
+/// The `alias` builtin, which defines the aliases given as `name=value`, and prints the others or all of them
+pub fn alias(args: &[String]) -> io::Result<i32> {
+    if args.is_empty() || args == ["-p"] {
+        for (name, value) in ALIASES.borrow().iter() {
+            print(name, value);
+        }
+        return Ok(0);
+    }
+
+    let mut status = 0;
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value)) if is_valid_name(name) => {
+                ALIASES
+                    .borrow_mut()
+                    .insert(String::from(name), String::from(value));
+            }
+            Some((name, _)) => {
+                eprintln!("alias: {name}: invalid alias name");
+                status = 1;
+            }
+            None => match get(arg) {
+                Some(value) => print(arg, &value),
+                None => {
+                    eprintln!("alias: {arg}: not found");
+                    status = 1;
+                }
+            },
+        }
+    }
+    Ok(status)
+}
+
+/// The `unalias` builtin, which removes the named aliases, or all of them with `-a`
+pub fn unalias(args: &[String]) -> io::Result<i32> {
+    match args {
+        [] => Err(io::Error::new_with_message(
+            io::ErrorKind::InvalidInput,
+            "unalias: usage: unalias [-a] name...",
+        )),
+        [opt] if opt == "-a" => {
+            ALIASES.borrow_mut().clear();
+            Ok(0)
+        }
+        names => {
+            let mut status = 0;
+            for name in names {
+                if ALIASES.borrow_mut().remove(&**name).is_none() {
+                    eprintln!("unalias: {name}: not found");
+                    status = 1;
+                }
+            }
+            Ok(status)
+        }
+    }
+}
+
+/// Whether `word` is an assignment like `NAME=value`, which can come before a command name
+fn is_assignment(word: &Word) -> bool {
+    matches!(
+        word.parts.first(),
+        Some(WordPart::Literal(lit))
+            if lit.split_once('=').is_some_and(|(name, _)| vars::is_valid_name(name))
+    )
+}
+
+/// An alias whose value is being read
+struct Active {
+    name: String,
+    /// How many of the pending tokens came from the value
+    remaining: usize,
+    /// Whether the value ends in a blank, so that the word after it is checked for an alias too
+    blank: bool,
+}
+
+/// Expands aliases in a stream of tokens, as produced by [`split_shell`]. See [`expand_aliases`].
+pub struct ExpandAliases<'a, I> {
+    tokens: I,
+    /// Tokens from the values of aliases, which are read before the rest of the input
+    pending: VecDeque<Result<Token<'a>, SyntaxError>>,
+    /// The aliases being expanded, innermost last, which aren't expanded again inside their own values
+    active: Vec<Active>,
+    /// Whether the next word is in the position of a command name
+    command: bool,
+    /// Set after a redirection, whose target is never a command name
+    redirect: bool,
+    /// Set after an alias whose value ends in a blank
+    check_next: bool,
+}
+
+/// Replaces each alias in the position of a command name with the tokens of its value, for [`parse_shell`][super::parse_shell].
+///
+/// The first word of the value is expanded again, unless it is an alias that is already being expanded.
+/// If the value ends in a blank, the word after it is also expanded, even if it isn't a command name.
+pub fn expand_aliases<'a, I: Iterator<Item = Result<Token<'a>, SyntaxError>>>(
+    tokens: I,
+) -> ExpandAliases<'a, I> {
+    ExpandAliases {
+        tokens,
+        pending: VecDeque::new(),
+        active: Vec::new(),
+        command: true,
+        redirect: false,
+        check_next: false,
+    }
+}
+
+impl<'a, I: Iterator<Item = Result<Token<'a>, SyntaxError>>> ExpandAliases<'a, I> {
+    /// Takes the next token, from the value of an alias if one is being read
+    fn take(&mut self) -> Option<Result<Token<'a>, SyntaxError>> {
+        while let Some(active) = self.active.last()
+            && active.remaining == 0
+        {
+            if active.blank {
+                self.check_next = true;
+            }
+            self.active.pop();
+        }
+
+        match self.pending.pop_front() {
+            Some(tok) => {
+                for active in &mut self.active {
+                    active.remaining -= 1;
+                }
+                Some(tok)
+            }
+            None => self.tokens.next(),
+        }
+    }
+
+    /// Replaces the alias `name`, found at `span`, with the tokens of `value`
+    fn expand(&mut self, name: String, value: &str, span: Span) {
+        // An error in the value can't be fixed by reading more input, so it is reported as the alias being unexpected
+        let error = SyntaxError {
+            kind: SyntaxErrorKind::UnexpectedToken(name.clone()),
+            span,
+        };
+        let tokens = split_shell(value)
+            .map(|tok| match tok {
+                // The value isn't part of the input, so its tokens are all placed at the alias
+                Ok(tok) => Ok(Token {
+                    kind: match tok.kind {
+                        TokenKind::Word(word) => TokenKind::Word(word.into_owned()),
+                        TokenKind::Op(op) => TokenKind::Op(op),
+                    },
+                    span,
+                }),
+                Err(_) => Err(error.clone()),
+            })
+            .collect::<Vec<_>>();
+
+        for active in &mut self.active {
+            active.remaining += tokens.len();
+        }
+        self.active.push(Active {
+            name,
+            remaining: tokens.len(),
+            blank: value.ends_with([' ', '\t']),
+        });
+        for tok in tokens.into_iter().rev() {
+            self.pending.push_front(tok);
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Result<Token<'a>, SyntaxError>>> Iterator for ExpandAliases<'a, I> {
+    type Item = Result<Token<'a>, SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let tok = match self.take()? {
+                Ok(tok) => tok,
+                Err(e) => return Some(Err(e)),
+            };
+            let word = match &tok.kind {
+                TokenKind::Word(word) => word,
+                TokenKind::Op(op) => {
+                    if op.is_redirect() {
+                        self.redirect = true;
+                    } else {
+                        self.command = *op != Op::RParen;
+                    }
+                    self.check_next = false;
+                    return Some(Ok(tok));
+                }
+            };
+            if self.redirect {
+                self.redirect = false;
+                return Some(Ok(tok));
+            }
+
+            let check = self.command || self.check_next;
+            self.check_next = false;
+            let lit = word.as_literal();
+            // Reserved words are recognized before aliases
+            if self.command
+                && let Some(kw) = lit.filter(|lit| KEYWORDS.contains(lit))
+            {
+                self.command = matches!(
+                    kw,
+                    "if" | "then" | "elif" | "else" | "while" | "until" | "do" | "{" | "!"
+                );
+                return Some(Ok(tok));
+            }
+
+            if check
+                && let Some(name) = lit
+                && !self.active.iter().any(|active| active.name == name)
+                && let Some(value) = get(name)
+            {
+                let name = String::from(name);
+                self.expand(name, &value, tok.span);
+                continue;
+            }
+
+            if !is_assignment(word) {
+                self.command = false;
+            }
+            return Some(Ok(tok));
+        }
+    }
+}
@@ -0,0 +1,508 @@
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use super::vars;
+
+/// Limit on how deeply variables whose values are themselves expressions are evaluated
+const MAX_RECURSION: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArithError {
+    DivisionByZero,
+    NegativeExponent,
+    InvalidNumber(String),
+    InvalidBase(String),
+    UnexpectedToken(String),
+    UnexpectedEnd,
+    NotAssignable,
+    RecursionLimit(String),
+}
+
+impl core::fmt::Display for ArithError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ArithError::DivisionByZero => f.write_str("division by 0"),
+            ArithError::NegativeExponent => f.write_str("exponent less than 0"),
+            ArithError::InvalidNumber(n) => write!(f, "value too great for base: {n}"),
+            ArithError::InvalidBase(n) => write!(f, "invalid arithmetic base: {n}"),
+            ArithError::UnexpectedToken(t) => write!(f, "syntax error: unexpected `{t}`"),
+            ArithError::UnexpectedEnd => f.write_str("syntax error: operand expected"),
+            ArithError::NotAssignable => {
+                f.write_str("attempted assignment to non-variable")
+            }
+            ArithError::RecursionLimit(v) => {
+                write!(f, "expression recursion level exceeded while evaluating `{v}`")
+            }
+        }
+    }
+}
+
+impl core::error::Error for ArithError {}
+
+pub type Result<T> = core::result::Result<T, ArithError>;
+
+/// Evaluates `expr` as a 64-bit signed integer expression with C operator precedence.
+///
+/// Variables are read from (and assigned to) the shell variable store. An empty expression evaluates to `0`.
+pub fn eval(expr: &str) -> Result<i64> {
+    eval_at_depth(expr, 0)
+}
+
+fn eval_at_depth(expr: &str, depth: usize) -> Result<i64> {
+    let toks = tokenize(expr)?;
+    if toks.is_empty() {
+        return Ok(0);
+    }
+    let mut parser = Parser { toks, pos: 0 };
+    let ast = parser.parse_comma()?;
+    if let Some(tok) = parser.toks.get(parser.pos) {
+        return Err(ArithError::UnexpectedToken(tok.to_string()));
+    }
+    ast.eval(depth)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tok<'a> {
+    Num(i64),
+    Ident(&'a str),
+    Op(&'static str),
+}
+
+impl core::fmt::Display for Tok<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Tok::Num(n) => n.fmt(f),
+            Tok::Ident(id) => f.write_str(id),
+            Tok::Op(op) => f.write_str(op),
+        }
+    }
+}
+
+// Longest operators first, so that the first prefix match is the correct one
+const OPERATORS: [&str; 39] = [
+    "<<=", ">>=", "**", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+=", "-=",
+    "*=", "/=", "%=", "&=", "^=", "|=", "+", "-", "*", "/", "%", "<", ">", "=", "!", "~", "&",
+    "^", "|", "?", ":", "(", ")", ",",
+];
+
+fn tokenize(expr: &str) -> Result<Vec<Tok<'_>>> {
+    let mut toks = Vec::new();
+    let mut rest = expr;
+    loop {
+        rest = rest.trim_start();
+        let Some(c) = rest.chars().next() else {
+            break;
+        };
+
+        if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '@' || c == '#'))
+                .unwrap_or(rest.len());
+            toks.push(Tok::Num(parse_number(&rest[..len])?));
+            rest = &rest[len..];
+        } else if c == '_' || c.is_ascii_alphabetic() || c == '$' {
+            // `$name` is accepted inside arithmetic as a plain variable reference
+            let body = rest.strip_prefix('$').unwrap_or(rest);
+            let len = body
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(body.len());
+            if len == 0 {
+                return Err(ArithError::UnexpectedToken("$".to_string()));
+            }
+            toks.push(Tok::Ident(&body[..len]));
+            rest = &body[len..];
+        } else {
+            let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) else {
+                return Err(ArithError::UnexpectedToken(c.to_string()));
+            };
+            toks.push(Tok::Op(op));
+            rest = &rest[op.len()..];
+        }
+    }
+    Ok(toks)
+}
+
+fn parse_number(lit: &str) -> Result<i64> {
+    let (base, digits) = if let Some((base, digits)) = lit.split_once('#') {
+        let base = base
+            .parse::<u32>()
+            .ok()
+            .filter(|b| (2..=64).contains(b))
+            .ok_or_else(|| ArithError::InvalidBase(lit.to_string()))?;
+        (base, digits)
+    } else if let Some(digits) = lit.strip_prefix("0x").or_else(|| lit.strip_prefix("0X")) {
+        (16, digits)
+    } else if lit.len() > 1 && lit.starts_with('0') {
+        (8, &lit[1..])
+    } else {
+        (10, lit)
+    };
+
+    if digits.is_empty() {
+        return Err(ArithError::InvalidNumber(lit.to_string()));
+    }
+
+    let mut val = 0i64;
+    for c in digits.chars() {
+        let digit = match c {
+            '0'..='9' => c as u32 - '0' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 10,
+            // Bases up to 36 are case-insensitive, beyond that upper case letters are distinct digits
+            'A'..='Z' if base <= 36 => c as u32 - 'A' as u32 + 10,
+            'A'..='Z' => c as u32 - 'A' as u32 + 36,
+            '@' => 62,
+            '_' => 63,
+            _ => return Err(ArithError::InvalidNumber(lit.to_string())),
+        };
+        if digit >= base {
+            return Err(ArithError::InvalidNumber(lit.to_string()));
+        }
+        val = val.wrapping_mul(base as i64).wrapping_add(digit as i64);
+    }
+    Ok(val)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinOp {
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Ne,
+    BitAnd,
+    BitXor,
+    BitOr,
+    And,
+    Or,
+}
+
+impl BinOp {
+    fn from_op(op: &str) -> Option<(Self, u8)> {
+        Some(match op {
+            "||" => (BinOp::Or, 1),
+            "&&" => (BinOp::And, 2),
+            "|" => (BinOp::BitOr, 3),
+            "^" => (BinOp::BitXor, 4),
+            "&" => (BinOp::BitAnd, 5),
+            "==" => (BinOp::Eq, 6),
+            "!=" => (BinOp::Ne, 6),
+            "<" => (BinOp::Lt, 7),
+            ">" => (BinOp::Gt, 7),
+            "<=" => (BinOp::Le, 7),
+            ">=" => (BinOp::Ge, 7),
+            "<<" => (BinOp::Shl, 8),
+            ">>" => (BinOp::Shr, 8),
+            "+" => (BinOp::Add, 9),
+            "-" => (BinOp::Sub, 9),
+            "*" => (BinOp::Mul, 10),
+            "/" => (BinOp::Div, 10),
+            "%" => (BinOp::Rem, 10),
+            "**" => (BinOp::Pow, 11),
+            _ => return None,
+        })
+    }
+
+    fn from_assign_op(op: &str) -> Option<Option<Self>> {
+        Some(match op {
+            "=" => None,
+            "*=" => Some(BinOp::Mul),
+            "/=" => Some(BinOp::Div),
+            "%=" => Some(BinOp::Rem),
+            "+=" => Some(BinOp::Add),
+            "-=" => Some(BinOp::Sub),
+            "<<=" => Some(BinOp::Shl),
+            ">>=" => Some(BinOp::Shr),
+            "&=" => Some(BinOp::BitAnd),
+            "^=" => Some(BinOp::BitXor),
+            "|=" => Some(BinOp::BitOr),
+            _ => return None,
+        })
+    }
+
+    fn apply(self, a: i64, b: i64) -> Result<i64> {
+        Ok(match self {
+            BinOp::Mul => a.wrapping_mul(b),
+            BinOp::Div if b == 0 => return Err(ArithError::DivisionByZero),
+            BinOp::Div => a.wrapping_div(b),
+            BinOp::Rem if b == 0 => return Err(ArithError::DivisionByZero),
+            BinOp::Rem => a.wrapping_rem(b),
+            BinOp::Pow if b < 0 => return Err(ArithError::NegativeExponent),
+            BinOp::Pow => a.wrapping_pow(b.min(u32::MAX as i64) as u32),
+            BinOp::Add => a.wrapping_add(b),
+            BinOp::Sub => a.wrapping_sub(b),
+            BinOp::Shl => a.wrapping_shl(b as u32),
+            BinOp::Shr => a.wrapping_shr(b as u32),
+            BinOp::Lt => (a < b) as i64,
+            BinOp::Gt => (a > b) as i64,
+            BinOp::Le => (a <= b) as i64,
+            BinOp::Ge => (a >= b) as i64,
+            BinOp::Eq => (a == b) as i64,
+            BinOp::Ne => (a != b) as i64,
+            BinOp::BitAnd => a & b,
+            BinOp::BitXor => a ^ b,
+            BinOp::BitOr => a | b,
+            BinOp::And => (a != 0 && b != 0) as i64,
+            BinOp::Or => (a != 0 || b != 0) as i64,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UnOp {
+    Neg,
+    Plus,
+    Not,
+    BitNot,
+}
+
+enum Expr<'a> {
+    Num(i64),
+    Var(&'a str),
+    Unary(UnOp, Box<Expr<'a>>),
+    Binary(BinOp, Box<Expr<'a>>, Box<Expr<'a>>),
+    Cond(Box<Expr<'a>>, Box<Expr<'a>>, Box<Expr<'a>>),
+    Assign(&'a str, Option<BinOp>, Box<Expr<'a>>),
+    /// `++x`/`--x`
+    PreInc(&'a str, i64),
+    /// `x++`/`x--`
+    PostInc(&'a str, i64),
+    Comma(Box<Expr<'a>>, Box<Expr<'a>>),
+}
+
+fn read_var(name: &str, depth: usize) -> Result<i64> {
+    let val = vars::get(name).unwrap_or_default();
+    let val = val.trim();
+    if val.is_empty() {
+        Ok(0)
+    } else if depth >= MAX_RECURSION {
+        Err(ArithError::RecursionLimit(name.to_string()))
+    } else {
+        // Like other shells, the value of a variable is itself evaluated as an expression
+        eval_at_depth(val, depth + 1)
+    }
+}
+
+fn write_var(name: &str, val: i64) -> i64 {
+    vars::set(name, val.to_string());
+    val
+}
+
+impl Expr<'_> {
+    fn eval(&self, depth: usize) -> Result<i64> {
+        match self {
+            Expr::Num(n) => Ok(*n),
+            Expr::Var(name) => read_var(name, depth),
+            Expr::Unary(op, e) => {
+                let v = e.eval(depth)?;
+                Ok(match op {
+                    UnOp::Neg => v.wrapping_neg(),
+                    UnOp::Plus => v,
+                    UnOp::Not => (v == 0) as i64,
+                    UnOp::BitNot => !v,
+                })
+            }
+            Expr::Binary(BinOp::And, a, b) => {
+                Ok((a.eval(depth)? != 0 && b.eval(depth)? != 0) as i64)
+            }
+            Expr::Binary(BinOp::Or, a, b) => {
+                Ok((a.eval(depth)? != 0 || b.eval(depth)? != 0) as i64)
+            }
+            Expr::Binary(op, a, b) => {
+                let a = a.eval(depth)?;
+                let b = b.eval(depth)?;
+                op.apply(a, b)
+            }
+            Expr::Cond(c, t, f) => {
+                if c.eval(depth)? != 0 {
+                    t.eval(depth)
+                } else {
+                    f.eval(depth)
+                }
+            }
+            Expr::Assign(name, op, e) => {
+                let val = match op {
+                    Some(op) => {
+                        let cur = read_var(name, depth)?;
+                        op.apply(cur, e.eval(depth)?)?
+                    }
+                    None => e.eval(depth)?,
+                };
+                Ok(write_var(name, val))
+            }
+            Expr::PreInc(name, by) => {
+                let val = read_var(name, depth)?.wrapping_add(*by);
+                Ok(write_var(name, val))
+            }
+            Expr::PostInc(name, by) => {
+                let val = read_var(name, depth)?;
+                write_var(name, val.wrapping_add(*by));
+                Ok(val)
+            }
+            Expr::Comma(a, b) => {
+                a.eval(depth)?;
+                b.eval(depth)
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    toks: Vec<Tok<'a>>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Tok<'a>> {
+        self.toks.get(self.pos).copied()
+    }
+
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.peek() {
+            Some(Tok::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn bump(&mut self) -> Option<Tok<'a>> {
+        let tok = self.peek();
+        self.pos += 1;
+        tok
+    }
+
+    fn expect(&mut self, op: &'static str) -> Result<()> {
+        match self.bump() {
+            Some(Tok::Op(o)) if o == op => Ok(()),
+            Some(tok) => Err(ArithError::UnexpectedToken(tok.to_string())),
+            None => Err(ArithError::UnexpectedEnd),
+        }
+    }
+
+    /// `++`/`--` that isn't adjacent to a variable is two unary (or a binary and a unary) operators, as in `1--1`
+    fn split_incdec(&mut self) {
+        if let Some(Tok::Op(op @ ("++" | "--"))) = self.peek() {
+            let single = if op == "++" { "+" } else { "-" };
+            self.toks[self.pos] = Tok::Op(single);
+            self.toks.insert(self.pos + 1, Tok::Op(single));
+        }
+    }
+
+    fn parse_comma(&mut self) -> Result<Expr<'a>> {
+        let mut lhs = self.parse_assign()?;
+        while self.peek_op() == Some(",") {
+            self.pos += 1;
+            let rhs = self.parse_assign()?;
+            lhs = Expr::Comma(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_assign(&mut self) -> Result<Expr<'a>> {
+        let lhs = self.parse_cond()?;
+        if let Some(op) = self.peek_op().and_then(BinOp::from_assign_op) {
+            let Expr::Var(name) = lhs else {
+                return Err(ArithError::NotAssignable);
+            };
+            self.pos += 1;
+            let rhs = self.parse_assign()?;
+            return Ok(Expr::Assign(name, op, Box::new(rhs)));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_cond(&mut self) -> Result<Expr<'a>> {
+        let cond = self.parse_binary(1)?;
+        if self.peek_op() == Some("?") {
+            self.pos += 1;
+            let then = self.parse_comma()?;
+            self.expect(":")?;
+            let otherwise = self.parse_cond()?;
+            return Ok(Expr::Cond(
+                Box::new(cond),
+                Box::new(then),
+                Box::new(otherwise),
+            ));
+        }
+        Ok(cond)
+    }
+
+    fn parse_binary(&mut self, min_prec: u8) -> Result<Expr<'a>> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            // A postfix `--` on something that isn't a variable is a subtraction of a negation
+            self.split_incdec();
+            let Some((op, prec)) = self.peek_op().and_then(BinOp::from_op) else {
+                break;
+            };
+            if prec < min_prec {
+                break;
+            }
+            self.pos += 1;
+            // `**` is right associative, everything else is left associative
+            let rhs = if op == BinOp::Pow {
+                self.parse_binary(prec)?
+            } else {
+                self.parse_binary(prec + 1)?
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr<'a>> {
+        match self.peek() {
+            Some(Tok::Op(op @ ("++" | "--"))) => {
+                if let Some(Tok::Ident(name)) = self.toks.get(self.pos + 1).copied() {
+                    self.pos += 2;
+                    let by = if op == "++" { 1 } else { -1 };
+                    return Ok(Expr::PreInc(name, by));
+                }
+                self.split_incdec();
+                self.parse_unary()
+            }
+            Some(Tok::Op(op @ ("-" | "+" | "!" | "~"))) => {
+                self.pos += 1;
+                let op = match op {
+                    "-" => UnOp::Neg,
+                    "+" => UnOp::Plus,
+                    "!" => UnOp::Not,
+                    _ => UnOp::BitNot,
+                };
+                Ok(Expr::Unary(op, Box::new(self.parse_unary()?)))
+            }
+            _ => self.parse_postfix(),
+        }
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr<'a>> {
+        match self.bump() {
+            Some(Tok::Num(n)) => Ok(Expr::Num(n)),
+            Some(Tok::Ident(name)) => match self.peek_op() {
+                Some(op @ ("++" | "--")) => {
+                    self.pos += 1;
+                    let by = if op == "++" { 1 } else { -1 };
+                    Ok(Expr::PostInc(name, by))
+                }
+                _ => Ok(Expr::Var(name)),
+            },
+            Some(Tok::Op("(")) => {
+                let inner = self.parse_comma()?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Some(tok) => Err(ArithError::UnexpectedToken(tok.to_string())),
+            None => Err(ArithError::UnexpectedEnd),
+        }
+    }
+}
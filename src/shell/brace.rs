@@ -0,0 +1,194 @@
+use alloc::{string::String, vec::Vec};
+
+use super::word::{Word, WordBuilder, WordPart};
+
+#[derive(Clone, Copy)]
+enum Piece<'w, 'a> {
+    Char(char, bool),
+    Part(&'w WordPart<'a>),
+}
+
+impl Piece<'_, '_> {
+    fn unquoted(&self) -> Option<char> {
+        match *self {
+            Piece::Char(c, false) => Some(c),
+            _ => None,
+        }
+    }
+}
+
+/// Performs brace expansion (`{a,b,c}`, `{1..10..2}`, `{a..z}`) on the unquoted parts of `word`.
+///
+/// Words without any brace expansion are returned unchanged.
+pub fn expand_braces<'a>(word: &Word<'a>) -> Vec<Word<'a>> {
+    let has_brace = word.parts.iter().any(|part| match part {
+        WordPart::Literal(lit) => lit.contains('{'),
+        _ => false,
+    });
+    if !has_brace {
+        return alloc::vec![word.clone()];
+    }
+
+    let mut pieces = Vec::new();
+    for part in &word.parts {
+        match part {
+            WordPart::Literal(lit) => pieces.extend(lit.chars().map(|c| Piece::Char(c, false))),
+            WordPart::Quoted(lit) => pieces.extend(lit.chars().map(|c| Piece::Char(c, true))),
+            part => pieces.push(Piece::Part(part)),
+        }
+    }
+
+    let mut out = Vec::new();
+    expand_pieces(&pieces, &mut out);
+    out.into_iter().map(|pieces| build_word(&pieces)).collect()
+}
+
+fn expand_pieces<'w, 'a>(pieces: &[Piece<'w, 'a>], out: &mut Vec<Vec<Piece<'w, 'a>>>) {
+    for (open, _) in pieces
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.unquoted() == Some('{'))
+    {
+        let Some(close) = matching_brace(&pieces[open + 1..]).map(|n| n + open + 1) else {
+            continue;
+        };
+        let prefix = &pieces[..open];
+        let body = &pieces[open + 1..close];
+        let suffix = &pieces[close + 1..];
+
+        let alternatives = split_alternatives(body);
+        if alternatives.len() > 1 {
+            for alt in alternatives {
+                let mut next = Vec::with_capacity(prefix.len() + alt.len() + suffix.len());
+                next.extend_from_slice(prefix);
+                next.extend_from_slice(alt);
+                next.extend_from_slice(suffix);
+                expand_pieces(&next, out);
+            }
+            return;
+        }
+
+        if let Some(seq) = sequence(body) {
+            for item in seq {
+                let mut next = Vec::with_capacity(prefix.len() + item.len() + suffix.len());
+                next.extend_from_slice(prefix);
+                next.extend(item.chars().map(|c| Piece::Char(c, false)));
+                next.extend_from_slice(suffix);
+                expand_pieces(&next, out);
+            }
+            return;
+        }
+    }
+
+    out.push(pieces.to_vec());
+}
+
+/// Finds the unquoted `}` that closes a brace group, given the pieces after the opening `{`
+fn matching_brace(pieces: &[Piece]) -> Option<usize> {
+    let mut depth = 0usize;
+    for (n, p) in pieces.iter().enumerate() {
+        match p.unquoted() {
+            Some('{') => depth += 1,
+            Some('}') if depth == 0 => return Some(n),
+            Some('}') => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits the body of a brace group on the unquoted commas that aren't nested in another group
+fn split_alternatives<'p, 'w, 'a>(body: &'p [Piece<'w, 'a>]) -> Vec<&'p [Piece<'w, 'a>]> {
+    let mut alternatives = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (n, p) in body.iter().enumerate() {
+        match p.unquoted() {
+            Some('{') => depth += 1,
+            Some('}') => depth = depth.saturating_sub(1),
+            Some(',') if depth == 0 => {
+                alternatives.push(&body[start..n]);
+                start = n + 1;
+            }
+            _ => {}
+        }
+    }
+    alternatives.push(&body[start..]);
+    alternatives
+}
+
+/// Expands a `x..y` or `x..y..incr` sequence body, where `x` and `y` are both integers or both single characters
+fn sequence(body: &[Piece]) -> Option<Vec<String>> {
+    let mut text = String::new();
+    for p in body {
+        text.push(p.unquoted()?);
+    }
+
+    let mut fields = text.split("..");
+    let (start, end) = (fields.next()?, fields.next()?);
+    let incr = match fields.next() {
+        Some(incr) => incr.parse::<i64>().ok()?.unsigned_abs().max(1),
+        None => 1,
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+
+    if let (Ok(x), Ok(y)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        // A leading zero on either end pads every item to the same width
+        let padded = |s: &str| {
+            let digits = s.strip_prefix('-').unwrap_or(s);
+            digits.len() > 1 && digits.starts_with('0')
+        };
+        let width = if padded(start) || padded(end) {
+            start.len().max(end.len())
+        } else {
+            0
+        };
+
+        Some(
+            range(x, y, incr)?
+                .map(|n| alloc::format!("{n:0width$}"))
+                .collect(),
+        )
+    } else {
+        let mut start_chars = start.chars();
+        let mut end_chars = end.chars();
+        let (Some(x), None, Some(y), None) = (
+            start_chars.next(),
+            start_chars.next(),
+            end_chars.next(),
+            end_chars.next(),
+        ) else {
+            return None;
+        };
+        if !x.is_ascii() || !y.is_ascii() {
+            return None;
+        }
+
+        Some(
+            range(x as i64, y as i64, incr)?
+                .map(|n| String::from(n as u8 as char))
+                .collect(),
+        )
+    }
+}
+
+/// The numbers from `x` to `y`, `incr` apart, or `None` if there are too many to count or `incr` is too large for a step
+fn range(x: i64, y: i64, incr: u64) -> Option<impl Iterator<Item = i64>> {
+    let count = (x.abs_diff(y) / incr).checked_add(1)?;
+    let incr = i64::try_from(incr).ok()?;
+    let step = if x <= y { incr } else { -incr };
+    Some((0..count).map(move |n| x.wrapping_add(step.wrapping_mul(n as i64))))
+}
+
+fn build_word<'a>(pieces: &[Piece<'_, 'a>]) -> Word<'a> {
+    let mut word = WordBuilder::new();
+    for p in pieces {
+        match *p {
+            Piece::Char(c, quoted) => word.push(c, quoted),
+            Piece::Part(part) => word.push_part(part.clone()),
+        }
+    }
+    word.finish()
+}
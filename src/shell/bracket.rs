@@ -0,0 +1,137 @@
+use alloc::{string::String, vec::Vec};
+
+/// The names of the character classes that can appear in a bracket expression, as in `[[:alpha:]]`
+pub const CLASSES: [&str; 12] = [
+    "alnum", "alpha", "blank", "cntrl", "digit", "graph", "lower", "print", "punct", "space",
+    "upper", "xdigit",
+];
+
+/// Matches `c` against a character class such as `[:alpha:]`
+pub fn class(name: &str, c: char) -> bool {
+    match name {
+        "alnum" => c.is_alphanumeric(),
+        "alpha" => c.is_alphabetic(),
+        "blank" => c == ' ' || c == '\t',
+        "cntrl" => c.is_control(),
+        "digit" => c.is_ascii_digit(),
+        "graph" => !c.is_whitespace() && !c.is_control(),
+        "lower" => c.is_lowercase(),
+        "print" => !c.is_control(),
+        "punct" => c.is_ascii_punctuation(),
+        "space" => c.is_whitespace(),
+        "upper" => c.is_uppercase(),
+        "xdigit" => c.is_ascii_hexdigit(),
+        _ => false,
+    }
+}
+
+#[derive(Clone)]
+pub enum Item {
+    Char(char),
+    Range(char, char),
+    /// A character class such as `[:alpha:]`, whose name isn't checked against [`CLASSES`]
+    Class(String),
+}
+
+pub enum BracketError {
+    /// There is no closing `]`, or no closing `:]`, `.]` or `=]` for a name
+    Unterminated,
+    /// A collating element or equivalence class of more than one character, which aren't supported
+    InvalidElement(String),
+}
+
+/// A bracket expression, such as `[a-z_]` or `[^[:space:]]`, as used by both shell patterns and regular expressions
+#[derive(Clone)]
+pub struct Bracket {
+    pub negated: bool,
+    pub items: Vec<Item>,
+}
+
+impl Bracket {
+    /// Parses a bracket expression from the characters after the opening `[`, each with whether it was quoted.
+    ///
+    /// It is negated by any of `negators` at the start, and quoted characters are never special.
+    /// Returns the expression and the characters after its closing `]`.
+    pub fn parse<I: Iterator<Item = (char, bool)> + Clone>(
+        mut chars: I,
+        negators: &[char],
+    ) -> Result<(Self, I), BracketError> {
+        let negated = match chars.clone().next() {
+            Some((c, false)) if negators.contains(&c) => {
+                chars.next();
+                true
+            }
+            _ => false,
+        };
+
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let (c, quoted) = chars.next().ok_or(BracketError::Unterminated)?;
+            // A `]` first in the list is part of it, rather than closing it
+            if c == ']' && !quoted && !first {
+                break;
+            }
+            first = false;
+
+            let lo = match (c, quoted, chars.clone().next()) {
+                ('[', false, Some((':', false))) => {
+                    chars.next();
+                    items.push(Item::Class(name(&mut chars, ':')?));
+                    continue;
+                }
+                // Only single-character collating elements and equivalence classes are supported, which are the character itself
+                ('[', false, Some((kind @ ('.' | '='), false))) => {
+                    chars.next();
+                    let name = name(&mut chars, kind)?;
+                    let mut name_chars = name.chars();
+                    match (name_chars.next(), name_chars.next()) {
+                        (Some(c), None) => c,
+                        _ => return Err(BracketError::InvalidElement(name)),
+                    }
+                }
+                (c, _, _) => c,
+            };
+
+            let mut rest = chars.clone();
+            if rest.next() == Some(('-', false))
+                && let Some((hi, hi_quoted)) = rest.next()
+                && (hi != ']' || hi_quoted)
+            {
+                chars = rest;
+                items.push(Item::Range(lo, hi));
+            } else {
+                items.push(Item::Char(lo));
+            }
+        }
+        Ok((Self { negated, items }, chars))
+    }
+
+    pub fn matches(&self, c: char) -> bool {
+        let found = self.items.iter().any(|item| match item {
+            Item::Char(x) => *x == c,
+            Item::Range(lo, hi) => (lo..=hi).contains(&&c),
+            Item::Class(name) => class(name, c),
+        });
+        found != self.negated
+    }
+}
+
+/// Reads the name of a `[:class:]`, `[.elem.]` or `[=equiv=]`, up to the unquoted `kind` and `]` that close it
+fn name<I: Iterator<Item = (char, bool)> + Clone>(
+    chars: &mut I,
+    kind: char,
+) -> Result<String, BracketError> {
+    let mut name = String::new();
+    loop {
+        let (c, quoted) = chars.next().ok_or(BracketError::Unterminated)?;
+        if c == kind && !quoted {
+            let mut rest = chars.clone();
+            if rest.next() == Some((']', false)) {
+                *chars = rest;
+                return Ok(name);
+            }
+        }
+        name.push(c);
+    }
+}
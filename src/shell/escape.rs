@@ -0,0 +1,133 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::io;
+
+/// Finds the closing `'` of a `$'...'` string, given the text after the opening quote.
+///
+/// Returns the length of the quoted body.
+pub fn ansi_c_len(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices();
+    while let Some((n, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\'' => return Some(n),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Reads up to `max` digits in base `radix`
+fn digits(s: &mut &str, radix: u32, max: usize) -> Option<u32> {
+    let len = s
+        .char_indices()
+        .take(max)
+        .find(|(_, c)| !c.is_digit(radix))
+        .map_or_else(|| s.len().min(max), |(n, _)| n);
+    if len == 0 {
+        return None;
+    }
+    let val = u32::from_str_radix(&s[..len], radix).ok()?;
+    *s = &s[len..];
+    Some(val)
+}
+
+/// The escapes understood by the different places that decode backslash escapes
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Dialect {
+    /// `$'...'` strings
+    AnsiC,
+    /// The format string of `printf`
+    Printf,
+    /// `echo -e` and the `%b` conversion of `printf`, where octal escapes start with `\0` and `\c` ends the output
+    Echo,
+}
+
+/// Decodes the backslash escapes in `s`.
+///
+/// Supports `\a \b \e \E \f \n \r \t \v \\`, `\xHH`, `\uXXXX` and `\UXXXXXXXX` in every dialect.
+/// Octal escapes are `\nnn` (and `\0nnn` in `$'...'`), or only `\0nnn` for `echo`.
+/// `$'...'` also supports `\' \" \?` and control characters `\cX`, `printf` supports `\' \" \?`, and `\c` in `echo` ends the output.
+///
+/// Returns the decoded bytes, and whether a `\c` ended the output early.
+/// Unknown escapes are kept as written.
+pub fn unescape(s: &str, dialect: Dialect) -> (Vec<u8>, bool) {
+    let mut out = Vec::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(n) = rest.find('\\') {
+        out.extend_from_slice(&io::encode_bytes(&rest[..n]));
+        rest = &rest[n + 1..];
+
+        let Some(c) = rest.chars().next() else {
+            out.push(b'\\');
+            break;
+        };
+        let mut escape = &rest[c.len_utf8()..];
+
+        let byte = match (c, dialect) {
+            ('a', _) => Some(0x07),
+            ('b', _) => Some(0x08),
+            ('e' | 'E', _) => Some(0x1b),
+            ('f', _) => Some(0x0c),
+            ('n', _) => Some(b'\n'),
+            ('r', _) => Some(b'\r'),
+            ('t', _) => Some(b'\t'),
+            ('v', _) => Some(0x0b),
+            ('\\', _) => Some(b'\\'),
+            ('\'' | '"' | '?', Dialect::AnsiC | Dialect::Printf) => Some(c as u8),
+            // `\0nnn` takes up to three digits after the `0`, `\nnn` up to three in total
+            ('0', Dialect::AnsiC | Dialect::Echo) => Some(digits(&mut escape, 8, 3).unwrap_or(0) as u8),
+            ('0'..='7', Dialect::AnsiC | Dialect::Printf) => {
+                escape = rest;
+                digits(&mut escape, 8, 3).map(|v| v as u8)
+            }
+            ('x', _) => digits(&mut escape, 16, 2).map(|v| v as u8),
+            ('c', Dialect::AnsiC) => match escape.chars().next() {
+                Some(x) if x.is_ascii() => {
+                    escape = &escape[1..];
+                    Some(if x == '?' { 0x7f } else { x as u8 & 0x1f })
+                }
+                _ => None,
+            },
+            ('c', Dialect::Echo) => return (out, true),
+            ('u' | 'U', _) => {
+                let max = if c == 'u' { 4 } else { 8 };
+                match digits(&mut escape, 16, max).and_then(char::from_u32) {
+                    Some(ch) => {
+                        let mut buf = [0; 4];
+                        out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+                        rest = escape;
+                        continue;
+                    }
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+
+        match byte {
+            Some(byte) => {
+                out.push(byte);
+                rest = escape;
+            }
+            None => out.push(b'\\'),
+        }
+    }
+    out.extend_from_slice(&io::encode_bytes(rest));
+    (out, false)
+}
+
+/// Decodes the body of a `$'...'` string.
+///
+/// As in other shells, a NUL character ends the string. Bytes from `\\x` or octal escapes that aren't valid UTF-8 are kept, as by [`io::decode_bytes`].
+pub fn ansi_c(body: &str) -> String {
+    let (mut out, _) = unescape(body, Dialect::AnsiC);
+    if let Some(nul) = out.iter().position(|&b| b == 0) {
+        out.truncate(nul);
+    }
+
+    io::decode_bytes(&out)
+}
@@ -0,0 +1,810 @@
+use alloc::{
+    borrow::Cow,
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use super::{
+    EnvVar, Redirect, ShellLine,
+    test::{BINARY_OPS, UNARY_OPS},
+    vars,
+    word::{Word, WordPart},
+};
+
+/// A range of the input, with the line and column (both starting at 1) of its start
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Op {
+    Semi,
+    Newline,
+    AndIf,
+    OrIf,
+    Pipe,
+    Amp,
+    LParen,
+    RParen,
+    /// `>`, which redirects output to a file
+    Great,
+    /// `>>`, which appends output to a file
+    DGreat,
+    /// `>&`, which makes a descriptor a copy of another one, or closes it
+    GreatAnd,
+    /// `<`, which redirects input from a file
+    Less,
+    /// `<&`, which makes a descriptor a copy of another one, or closes it
+    LessAnd,
+    /// `<>`, which opens a file for both reading and writing
+    LessGreat,
+}
+
+impl Op {
+    /// Operators in the order they are matched, so that `&&` is found before `&`
+    const ALL: [Op; 14] = [
+        Op::AndIf,
+        Op::OrIf,
+        Op::Semi,
+        Op::Newline,
+        Op::Pipe,
+        Op::Amp,
+        Op::LParen,
+        Op::RParen,
+        Op::DGreat,
+        Op::GreatAnd,
+        Op::Great,
+        Op::LessAnd,
+        Op::LessGreat,
+        Op::Less,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Op::Semi => ";",
+            Op::Newline => "\n",
+            Op::AndIf => "&&",
+            Op::OrIf => "||",
+            Op::Pipe => "|",
+            Op::Amp => "&",
+            Op::LParen => "(",
+            Op::RParen => ")",
+            Op::Great => ">",
+            Op::DGreat => ">>",
+            Op::GreatAnd => ">&",
+            Op::Less => "<",
+            Op::LessAnd => "<&",
+            Op::LessGreat => "<>",
+        }
+    }
+
+    /// Whether the operator is a redirection, which is followed by a file or descriptor
+    pub const fn is_redirect(self) -> bool {
+        matches!(
+            self,
+            Op::Great | Op::DGreat | Op::GreatAnd | Op::Less | Op::LessAnd | Op::LessGreat
+        )
+    }
+
+    /// The descriptor that a redirection applies to when no number is given before it, which is stdin for `<` and stdout for `>`
+    pub const fn default_fd(self) -> u32 {
+        match self {
+            Op::Less | Op::LessAnd | Op::LessGreat => 0,
+            _ => 1,
+        }
+    }
+
+    /// Finds the operator at the start of `s`
+    pub fn parse(s: &str) -> Option<Op> {
+        Self::ALL.into_iter().find(|op| s.starts_with(op.as_str()))
+    }
+}
+
+impl core::fmt::Display for Op {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Op::Newline => f.write_str("newline"),
+            op => f.write_str(op.as_str()),
+        }
+    }
+}
+
+pub enum TokenKind<'a> {
+    Word(Word<'a>),
+    Op(Op),
+}
+
+pub struct Token<'a> {
+    pub kind: TokenKind<'a>,
+    pub span: Span,
+}
+
+impl Token<'_> {
+    /// Returns the token's text if it is a reserved word, like `if` or `done`
+    fn keyword(&self) -> Option<&str> {
+        match &self.kind {
+            TokenKind::Word(word) => word.as_literal().filter(|w| KEYWORDS.contains(w)),
+            TokenKind::Op(_) => None,
+        }
+    }
+}
+
+impl core::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.kind {
+            TokenKind::Word(word) => word.fmt(f),
+            TokenKind::Op(op) => op.fmt(f),
+        }
+    }
+}
+
+/// Reserved words, which are recognized when they are an unquoted word in the position of a command name
+pub(crate) const KEYWORDS: [&str; 15] = [
+    "if", "then", "elif", "else", "fi", "while", "until", "do", "done", "for", "{", "}", "!", "[[",
+    "]]",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyntaxErrorKind {
+    /// A token that can't appear where it was found
+    UnexpectedToken(String),
+    /// The input ended where a command was expected, such as after `&&`
+    UnexpectedEnd,
+    /// An open quote, and the quote character that is needed to close it
+    UnterminatedQuote(char),
+    /// A `\` at the end of the input, which continues the line
+    Continuation,
+    /// The input ended inside a compound command, before the keyword needed to continue or close it
+    Missing(&'static str),
+}
+
+/// An error in the syntax of the input, and where it was found
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub kind: SyntaxErrorKind,
+    pub span: Span,
+}
+
+impl SyntaxError {
+    /// Whether the error is caused by the input ending early, so that reading more lines may complete it
+    pub fn is_incomplete(&self) -> bool {
+        !matches!(self.kind, SyntaxErrorKind::UnexpectedToken(_))
+    }
+
+    /// Shows the line of `input` that the error is on, with the error's span underlined
+    pub fn snippet<'e>(&'e self, input: &'e str) -> Snippet<'e> {
+        Snippet { err: self, input }
+    }
+}
+
+impl core::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.kind {
+            SyntaxErrorKind::UnexpectedToken(tok) => {
+                write!(f, "syntax error near unexpected token `{tok}`")
+            }
+            SyntaxErrorKind::UnexpectedEnd => f.write_str("syntax error: unexpected end of input"),
+            SyntaxErrorKind::UnterminatedQuote(q) => {
+                write!(f, "unterminated quote (missing closing `{q}`)")
+            }
+            SyntaxErrorKind::Continuation => f.write_str("unexpected end of input after `\\`"),
+            SyntaxErrorKind::Missing(kw) => write!(f, "syntax error: missing `{kw}`"),
+        }
+    }
+}
+
+impl core::error::Error for SyntaxError {}
+
+pub struct Snippet<'e> {
+    err: &'e SyntaxError,
+    input: &'e str,
+}
+
+impl core::fmt::Display for Snippet<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let span = self.err.span;
+        let line_start = self.input[..span.start].rfind('\n').map_or(0, |n| n + 1);
+        let line_end = self.input[span.start..]
+            .find('\n')
+            .map_or(self.input.len(), |n| span.start + n);
+        let line = &self.input[line_start..line_end];
+        writeln!(f, "  {line}")?;
+
+        f.write_str("  ")?;
+        // Tabs are kept so that the carets line up with the text above them
+        for c in self.input[line_start..span.start].chars() {
+            f.write_str(if c == '\t' { "\t" } else { " " })?;
+        }
+        let width = self.input[span.start..span.end.clamp(span.start, line_end)]
+            .chars()
+            .count();
+        for _ in 0..width.max(1) {
+            f.write_str("^")?;
+        }
+        writeln!(f)
+    }
+}
+
+/// A sequence of commands, separated by `;` or newlines
+pub struct List<'a>(pub Vec<AndOr<'a>>);
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Connector {
+    And,
+    Or,
+}
+
+/// Commands joined by `&&` and `||`, which are run depending on the status of the command before them
+pub struct AndOr<'a> {
+    pub first: Pipeline<'a>,
+    pub rest: Vec<(Connector, Pipeline<'a>)>,
+}
+
+pub struct Pipeline<'a> {
+    /// Set by a leading `!`, which inverts the status of the command
+    pub negated: bool,
+    pub command: Command<'a>,
+}
+
+pub enum Command<'a> {
+    Simple(ShellLine<'a>),
+    /// `{ list; }`
+    Group(List<'a>),
+    /// `if list; then list; [elif list; then list;]... [else list;] fi`
+    If {
+        branches: Vec<(List<'a>, List<'a>)>,
+        otherwise: Option<List<'a>>,
+    },
+    /// `while list; do list; done`, or `until` if `until` is set
+    Loop {
+        until: bool,
+        cond: List<'a>,
+        body: List<'a>,
+    },
+    /// `for name [in word...]; do list; done`, where no `in` iterates over the positional parameters
+    For {
+        var: String,
+        words: Option<Vec<Word<'a>>>,
+        body: List<'a>,
+    },
+    /// `[[ expression ]]`
+    Cond(CondExpr<'a>),
+}
+
+/// The expression of a `[[ ]]` command
+pub enum CondExpr<'a> {
+    /// A word on its own, which is true if it is not empty
+    Word(Word<'a>),
+    /// A unary operator, such as `-f file`
+    Unary(&'static str, Word<'a>),
+    /// A binary operator, such as `a == b`
+    Binary(Word<'a>, &'static str, Word<'a>),
+    Not(Box<CondExpr<'a>>),
+    And(Box<CondExpr<'a>>, Box<CondExpr<'a>>),
+    Or(Box<CondExpr<'a>>, Box<CondExpr<'a>>),
+}
+
+/// Parses a complete program from the tokens produced by [`split_shell`][super::split_shell]
+pub fn parse_shell<'a, I: Iterator<Item = Result<Token<'a>, SyntaxError>>>(
+    iter: I,
+) -> Result<List<'a>, SyntaxError> {
+    let mut parser = Parser {
+        tokens: iter,
+        peeked: None,
+        last: Span {
+            start: 0,
+            end: 0,
+            line: 1,
+            col: 1,
+        },
+    };
+    let list = parser.parse_list(&[])?;
+    match parser.next()? {
+        Some(tok) => Err(parser.unexpected(tok)),
+        None => Ok(list),
+    }
+}
+
+struct Parser<'a, I> {
+    tokens: I,
+    peeked: Option<Token<'a>>,
+    /// The span of the last token, where an unexpected end of input is reported
+    last: Span,
+}
+
+impl<'a, I: Iterator<Item = Result<Token<'a>, SyntaxError>>> Parser<'a, I> {
+    fn peek(&mut self) -> Result<Option<&Token<'a>>, SyntaxError> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next().transpose()?;
+            if let Some(tok) = &self.peeked {
+                self.last = tok.span;
+            }
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn next(&mut self) -> Result<Option<Token<'a>>, SyntaxError> {
+        self.peek()?;
+        Ok(self.peeked.take())
+    }
+
+    /// Consumes the next token if it is a word
+    fn next_word(&mut self) -> Result<Option<Word<'a>>, SyntaxError> {
+        self.peek()?;
+        match self.peeked.take() {
+            Some(Token {
+                kind: TokenKind::Word(word),
+                ..
+            }) => Ok(Some(word)),
+            tok => {
+                self.peeked = tok;
+                Ok(None)
+            }
+        }
+    }
+
+    fn peek_op(&mut self) -> Result<Option<Op>, SyntaxError> {
+        Ok(self.peek()?.and_then(|tok| match tok.kind {
+            TokenKind::Op(op) => Some(op),
+            TokenKind::Word(_) => None,
+        }))
+    }
+
+    fn peek_keyword(&mut self) -> Result<Option<&str>, SyntaxError> {
+        Ok(self.peek()?.and_then(Token::keyword))
+    }
+
+    fn unexpected(&self, tok: Token<'a>) -> SyntaxError {
+        SyntaxError {
+            kind: SyntaxErrorKind::UnexpectedToken(tok.to_string()),
+            span: tok.span,
+        }
+    }
+
+    fn skip_newlines(&mut self) -> Result<(), SyntaxError> {
+        while self.peek_op()? == Some(Op::Newline) {
+            self.next()?;
+        }
+        Ok(())
+    }
+
+    /// Parses commands until the end of input or one of the `terminators` keywords
+    fn parse_list(&mut self, terminators: &[&str]) -> Result<List<'a>, SyntaxError> {
+        let mut list = Vec::new();
+        loop {
+            self.skip_newlines()?;
+            match self.peek()? {
+                None => break,
+                Some(tok) if tok.keyword().is_some_and(|kw| terminators.contains(&kw)) => break,
+                Some(_) => {}
+            }
+
+            list.push(self.parse_and_or()?);
+
+            match self.peek_op()? {
+                Some(Op::Semi | Op::Newline) => {
+                    self.next()?;
+                }
+                None if self.peek()?.is_none() => break,
+                // A keyword may directly follow a compound command, as in `if a; then { b; } fi`
+                None if self
+                    .peek_keyword()?
+                    .is_some_and(|kw| terminators.contains(&kw)) =>
+                {
+                    break;
+                }
+                _ => {
+                    let tok = self.next()?.unwrap();
+                    return Err(self.unexpected(tok));
+                }
+            }
+        }
+        Ok(List(list))
+    }
+
+    /// Parses the body of a compound command, which must contain at least one command.
+    ///
+    /// `open` is the keyword that began the compound command, and `expected` is the keyword needed next if the input ends.
+    fn parse_body(
+        &mut self,
+        terminators: &[&str],
+        open: Span,
+        expected: &'static str,
+    ) -> Result<List<'a>, SyntaxError> {
+        let list = self.parse_list(terminators)?;
+        if list.0.is_empty() {
+            return match self.next()? {
+                Some(tok) => Err(self.unexpected(tok)),
+                None => Err(SyntaxError {
+                    kind: SyntaxErrorKind::Missing(expected),
+                    span: open,
+                }),
+            };
+        }
+        Ok(list)
+    }
+
+    /// Consumes the keyword `kw`, reporting it as missing from the compound command starting at `open` if the input ends
+    fn expect_keyword(&mut self, kw: &'static str, open: Span) -> Result<(), SyntaxError> {
+        match self.next()? {
+            Some(tok) if tok.keyword() == Some(kw) => Ok(()),
+            Some(tok) => Err(self.unexpected(tok)),
+            None => Err(SyntaxError {
+                kind: SyntaxErrorKind::Missing(kw),
+                span: open,
+            }),
+        }
+    }
+
+    fn parse_and_or(&mut self) -> Result<AndOr<'a>, SyntaxError> {
+        let first = self.parse_pipeline()?;
+        let mut rest = Vec::new();
+        loop {
+            let connector = match self.peek_op()? {
+                Some(Op::AndIf) => Connector::And,
+                Some(Op::OrIf) => Connector::Or,
+                _ => break,
+            };
+            self.next()?;
+            self.skip_newlines()?;
+            rest.push((connector, self.parse_pipeline()?));
+        }
+        Ok(AndOr { first, rest })
+    }
+
+    fn parse_pipeline(&mut self) -> Result<Pipeline<'a>, SyntaxError> {
+        let negated = self.peek_keyword()? == Some("!");
+        if negated {
+            self.next()?;
+        }
+        Ok(Pipeline {
+            negated,
+            command: self.parse_command()?,
+        })
+    }
+
+    fn parse_command(&mut self) -> Result<Command<'a>, SyntaxError> {
+        let Some(tok) = self.peek()? else {
+            return Err(SyntaxError {
+                kind: SyntaxErrorKind::UnexpectedEnd,
+                span: self.last,
+            });
+        };
+        if let TokenKind::Op(op) = tok.kind
+            && !op.is_redirect()
+        {
+            let tok = self.next()?.unwrap();
+            return Err(self.unexpected(tok));
+        }
+
+        match tok.keyword() {
+            Some("if") => self.parse_if(),
+            Some(kw @ ("while" | "until")) => {
+                let until = kw == "until";
+                let open = self.next()?.unwrap().span;
+                let cond = self.parse_body(&["do"], open, "do")?;
+                self.expect_keyword("do", open)?;
+                let body = self.parse_body(&["done"], open, "done")?;
+                self.expect_keyword("done", open)?;
+                Ok(Command::Loop { until, cond, body })
+            }
+            Some("for") => self.parse_for(),
+            Some("{") => {
+                let open = self.next()?.unwrap().span;
+                let body = self.parse_body(&["}"], open, "}")?;
+                self.expect_keyword("}", open)?;
+                Ok(Command::Group(body))
+            }
+            Some("[[") => {
+                let open = self.next()?.unwrap().span;
+                let expr = self.parse_cond_or(open)?;
+                self.expect_keyword("]]", open)?;
+                Ok(Command::Cond(expr))
+            }
+            Some("then" | "elif" | "else" | "fi" | "do" | "done" | "}" | "!" | "]]") => {
+                let tok = self.next()?.unwrap();
+                Err(self.unexpected(tok))
+            }
+            _ => self.parse_simple(),
+        }
+    }
+
+    fn parse_if(&mut self) -> Result<Command<'a>, SyntaxError> {
+        let open = self.next()?.unwrap().span;
+        let mut branches = Vec::new();
+        let mut otherwise = None;
+        loop {
+            let cond = self.parse_body(&["then"], open, "then")?;
+            self.expect_keyword("then", open)?;
+            let body = self.parse_body(&["elif", "else", "fi"], open, "fi")?;
+            branches.push((cond, body));
+
+            match self.next()? {
+                Some(tok) if tok.keyword() == Some("elif") => {}
+                Some(tok) if tok.keyword() == Some("else") => {
+                    otherwise = Some(self.parse_body(&["fi"], open, "fi")?);
+                    self.expect_keyword("fi", open)?;
+                    break;
+                }
+                Some(tok) if tok.keyword() == Some("fi") => break,
+                Some(tok) => return Err(self.unexpected(tok)),
+                None => {
+                    return Err(SyntaxError {
+                        kind: SyntaxErrorKind::Missing("fi"),
+                        span: open,
+                    });
+                }
+            }
+        }
+        Ok(Command::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    fn parse_for(&mut self) -> Result<Command<'a>, SyntaxError> {
+        let open = self.next()?.unwrap().span;
+        let var = match self.next()? {
+            Some(tok) if tok.keyword().is_none() => match &tok.kind {
+                TokenKind::Word(word) if word.as_literal().is_some_and(vars::is_valid_name) => {
+                    word.to_string()
+                }
+                _ => return Err(self.unexpected(tok)),
+            },
+            Some(tok) => return Err(self.unexpected(tok)),
+            None => {
+                return Err(SyntaxError {
+                    kind: SyntaxErrorKind::Missing("do"),
+                    span: open,
+                });
+            }
+        };
+
+        self.skip_newlines()?;
+        let mut words = None;
+        if self
+            .peek()?
+            .is_some_and(|tok| matches!(&tok.kind, TokenKind::Word(w) if w.as_literal() == Some("in")))
+        {
+            self.next()?;
+            let mut list = Vec::new();
+            while let Some(word) = self.next_word()? {
+                list.push(word);
+            }
+            words = Some(list);
+
+            match self.next()? {
+                Some(Token {
+                    kind: TokenKind::Op(Op::Semi | Op::Newline),
+                    ..
+                }) => {}
+                Some(tok) => return Err(self.unexpected(tok)),
+                None => {
+                    return Err(SyntaxError {
+                        kind: SyntaxErrorKind::Missing("do"),
+                        span: open,
+                    });
+                }
+            }
+        } else if self.peek_op()? == Some(Op::Semi) {
+            self.next()?;
+        }
+
+        self.skip_newlines()?;
+        self.expect_keyword("do", open)?;
+        let body = self.parse_body(&["done"], open, "done")?;
+        self.expect_keyword("done", open)?;
+        Ok(Command::For { var, words, body })
+    }
+
+    fn parse_cond_or(&mut self, open: Span) -> Result<CondExpr<'a>, SyntaxError> {
+        let mut expr = self.parse_cond_and(open)?;
+        // Newlines are allowed anywhere between the operands and operators of `[[ ]]`
+        self.skip_newlines()?;
+        while self.peek_op()? == Some(Op::OrIf) {
+            self.next()?;
+            let rhs = self.parse_cond_and(open)?;
+            expr = CondExpr::Or(Box::new(expr), Box::new(rhs));
+            self.skip_newlines()?;
+        }
+        Ok(expr)
+    }
+
+    fn parse_cond_and(&mut self, open: Span) -> Result<CondExpr<'a>, SyntaxError> {
+        let mut expr = self.parse_cond_not(open)?;
+        self.skip_newlines()?;
+        while self.peek_op()? == Some(Op::AndIf) {
+            self.next()?;
+            let rhs = self.parse_cond_not(open)?;
+            expr = CondExpr::And(Box::new(expr), Box::new(rhs));
+            self.skip_newlines()?;
+        }
+        Ok(expr)
+    }
+
+    fn parse_cond_not(&mut self, open: Span) -> Result<CondExpr<'a>, SyntaxError> {
+        self.skip_newlines()?;
+        if self.peek_keyword()? == Some("!") {
+            self.next()?;
+            return Ok(CondExpr::Not(Box::new(self.parse_cond_not(open)?)));
+        }
+        self.parse_cond_primary(open)
+    }
+
+    /// Consumes the next token as an operand of a `[[ ]]` expression, which can't be `]]`
+    fn cond_operand(&mut self, open: Span) -> Result<Word<'a>, SyntaxError> {
+        match self.next()? {
+            Some(Token {
+                kind: TokenKind::Word(word),
+                ..
+            }) if word.as_literal() != Some("]]") => Ok(word),
+            Some(tok) => Err(self.unexpected(tok)),
+            None => Err(SyntaxError {
+                kind: SyntaxErrorKind::Missing("]]"),
+                span: open,
+            }),
+        }
+    }
+
+    /// Consumes the regular expression following `=~`.
+    ///
+    /// The characters `(`, `)` and `|` are part of the expression rather than operators, so adjacent tokens are joined into one word.
+    fn cond_regex(&mut self, open: Span) -> Result<Word<'a>, SyntaxError> {
+        let mut parts = Vec::new();
+        let mut end = None;
+        loop {
+            let take = match self.peek()? {
+                Some(tok) if end.is_none_or(|end| tok.span.start == end) => match &tok.kind {
+                    TokenKind::Word(word) => end.is_some() || word.as_literal() != Some("]]"),
+                    TokenKind::Op(op) => {
+                        matches!(op, Op::LParen | Op::RParen | Op::Pipe | Op::OrIf)
+                    }
+                },
+                _ => false,
+            };
+            if !take {
+                break;
+            }
+
+            let tok = self.next()?.unwrap();
+            end = Some(tok.span.end);
+            match tok.kind {
+                TokenKind::Word(word) => parts.extend(word.parts),
+                TokenKind::Op(op) => parts.push(WordPart::Literal(Cow::Borrowed(op.as_str()))),
+            }
+        }
+
+        if parts.is_empty() {
+            return self.cond_operand(open);
+        }
+        Ok(Word { parts })
+    }
+
+    fn parse_cond_primary(&mut self, open: Span) -> Result<CondExpr<'a>, SyntaxError> {
+        if self.peek_op()? == Some(Op::LParen) {
+            self.next()?;
+            let expr = self.parse_cond_or(open)?;
+            self.skip_newlines()?;
+            return match self.next()? {
+                Some(Token {
+                    kind: TokenKind::Op(Op::RParen),
+                    ..
+                }) => Ok(expr),
+                Some(tok) => Err(self.unexpected(tok)),
+                None => Err(SyntaxError {
+                    kind: SyntaxErrorKind::Missing("]]"),
+                    span: open,
+                }),
+            };
+        }
+
+        let word = self.cond_operand(open)?;
+        let unary = word
+            .as_literal()
+            .and_then(|lit| UNARY_OPS.iter().find(|&&op| op == lit));
+        if let Some(&op) = unary {
+            return Ok(CondExpr::Unary(op, self.cond_operand(open)?));
+        }
+
+        if self
+            .peek()?
+            .is_some_and(|tok| matches!(&tok.kind, TokenKind::Word(w) if w.as_literal() == Some("=~")))
+        {
+            self.next()?;
+            return Ok(CondExpr::Binary(word, "=~", self.cond_regex(open)?));
+        }
+
+        let binary = self.peek()?.and_then(|tok| match &tok.kind {
+            TokenKind::Word(w) => w
+                .as_literal()
+                .and_then(|lit| BINARY_OPS.iter().find(|&&op| op == lit)),
+            // `<` and `>` are string comparisons inside `[[ ]]`, rather than redirections
+            TokenKind::Op(op @ (Op::Less | Op::Great)) => {
+                BINARY_OPS.iter().find(|&&bin| bin == op.as_str())
+            }
+            TokenKind::Op(_) => None,
+        });
+        if let Some(&op) = binary {
+            self.next()?;
+            return Ok(CondExpr::Binary(word, op, self.cond_operand(open)?));
+        }
+        Ok(CondExpr::Word(word))
+    }
+
+    fn parse_simple(&mut self) -> Result<Command<'a>, SyntaxError> {
+        let mut line = ShellLine {
+            env: Vec::new(),
+            command: None,
+            args: Vec::new(),
+            redirects: Vec::new(),
+        };
+        loop {
+            let (fd, op) = match self.peek_op()? {
+                Some(op) if op.is_redirect() => {
+                    self.next()?;
+                    (op.default_fd(), op)
+                }
+                Some(_) => break,
+                None => {
+                    let Some(Token {
+                        kind: TokenKind::Word(word),
+                        span,
+                    }) = self.next()?
+                    else {
+                        break;
+                    };
+                    // A number written directly before a redirection is the descriptor that it redirects
+                    let fd = word
+                        .as_literal()
+                        .filter(|lit| lit.bytes().all(|b| b.is_ascii_digit()))
+                        .and_then(|lit| lit.parse().ok());
+                    match (fd, self.peek()?) {
+                        (
+                            Some(fd),
+                            Some(Token {
+                                kind: TokenKind::Op(op),
+                                span: op_span,
+                            }),
+                        ) if op.is_redirect() && op_span.start == span.end => {
+                            let op = *op;
+                            self.next()?;
+                            (fd, op)
+                        }
+                        _ => {
+                            if line.command.is_some() {
+                                line.args.push(word);
+                                continue;
+                            }
+                            match word.split_assignment() {
+                                Ok((key, val)) => line.env.push(EnvVar { key, val }),
+                                Err(word) => line.command = Some(word),
+                            }
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            let target = match self.next()? {
+                Some(Token {
+                    kind: TokenKind::Word(word),
+                    ..
+                }) => word,
+                Some(tok) => return Err(self.unexpected(tok)),
+                None => {
+                    return Err(SyntaxError {
+                        kind: SyntaxErrorKind::UnexpectedEnd,
+                        span: self.last,
+                    });
+                }
+            };
+            line.redirects.push(Redirect { fd, op, target });
+        }
+        Ok(Command::Simple(line))
+    }
+}
@@ -0,0 +1,370 @@
+use core::cell::{LazyCell, RefCell};
+
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+use lilium_sys::sys::{
+    error::DOES_NOT_EXIST,
+    fs::{CloseFile, FileHandle},
+    handle::HandlePtr,
+    kstr::KCSlice,
+    process::{CreateProcess, CreateProcessOption, ProcessHandle},
+    result::SysResult,
+};
+
+use super::{BUILTINS, alias, parse::KEYWORDS, vars};
+use crate::{eprintln, io, println};
+
+/// A directory from `PATH`, which is kept open to look commands up in
+struct Dir {
+    path: String,
+    hdl: HandlePtr<FileHandle>,
+}
+
+/// The directories in `PATH`, opened when they are first needed after `PATH` is set
+#[thread_local]
+static PATH: RefCell<Option<Vec<Dir>>> = RefCell::new(None);
+
+fn open_path() -> Vec<Dir> {
+    // Without `PATH`, only commands with a `/` can be run
+    let Some(path) = vars::get("PATH") else {
+        return Vec::new();
+    };
+    let mut dirs = Vec::new();
+    for dir in path.split(':') {
+        // An empty entry is the current directory
+        let dir = if dir.is_empty() { "." } else { dir };
+        match io::open_dir(dir) {
+            Ok(hdl) => dirs.push(Dir {
+                path: String::from(dir),
+                hdl,
+            }),
+            Err(e) if super::xtrace() => eprintln!("PATH: {dir}: {e}"),
+            Err(_) => {}
+        }
+    }
+    dirs
+}
+
+/// Calls `f` with the directories in `PATH`, opening them if `PATH` has changed
+fn with_path<R>(f: impl FnOnce(&[Dir]) -> R) -> R {
+    let mut path = PATH.borrow_mut();
+    f(path.get_or_insert_with(open_path))
+}
+
+/// Closes the directories from the old value of `PATH` and forgets the commands found in them, after `PATH` is set
+pub fn reset() {
+    if let Some(dirs) = PATH.borrow_mut().take() {
+        for dir in dirs {
+            unsafe {
+                CloseFile(dir.hdl);
+            }
+        }
+    }
+    HASH.borrow_mut().clear();
+}
+
+/// Where a command was found in `PATH`, and how many times it has been run from there
+struct Hashed {
+    dir: String,
+    hits: u32,
+}
+
+/// The commands that have been found in `PATH`, so that running them again doesn't need a search
+#[thread_local]
+static HASH: LazyCell<RefCell<BTreeMap<String, Hashed>>> =
+    LazyCell::new(|| RefCell::new(BTreeMap::new()));
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{dir}{name}")
+    } else {
+        format!("{dir}/{name}")
+    }
+}
+
+/// The directories in `PATH` that contain a file named `name`, in order.
+///
+/// Unless `all` is set, the search stops at the first one.
+fn search(name: &str, all: bool) -> Vec<String> {
+    with_path(|dirs| {
+        let mut found = Vec::new();
+        for dir in dirs {
+            if io::metadata_at(dir.hdl, name).is_ok_and(|meta| meta.is_file()) {
+                found.push(dir.path.clone());
+                if !all {
+                    break;
+                }
+            }
+        }
+        found
+    })
+}
+
+/// Finds the path of the command `name`, which is searched for in `PATH` unless it contains a `/` or was remembered
+pub fn find(name: &str) -> Option<String> {
+    if name.contains('/') {
+        return io::metadata(name).is_ok().then(|| String::from(name));
+    }
+    if let Some(hashed) = HASH.borrow().get(name) {
+        return Some(join(&hashed.dir, name));
+    }
+    search(name, false).first().map(|dir| join(dir, name))
+}
+
+/// Starts the command `name`, looking it up in `PATH` unless it contains a `/`.
+///
+/// The directory a command is found in is remembered, and tried first the next time it is run.
+pub fn spawn(name: &str, opts: &[CreateProcessOption]) -> io::Result<HandlePtr<ProcessHandle>> {
+    let path = io::encode_bytes(name);
+    let create = |base: HandlePtr<FileHandle>| -> Result<HandlePtr<ProcessHandle>, SysResult> {
+        let mut hdl = HandlePtr::null();
+        let res = unsafe {
+            CreateProcess(
+                &mut hdl,
+                base,
+                &io::kstr(&path),
+                &KCSlice::from_slice(opts),
+            )
+        };
+        if res < 0 { Err(res) } else { Ok(hdl) }
+    };
+
+    if name.contains('/') {
+        return create(HandlePtr::null()).map_err(io::Error::from_raw_os_error);
+    }
+
+    with_path(|dirs| {
+        let hashed = HASH.borrow().get(name).map(|hashed| hashed.dir.clone());
+        if let Some(hashed) = hashed {
+            if let Some(dir) = dirs.iter().find(|dir| dir.path == hashed)
+                && let Ok(proc) = create(dir.hdl)
+            {
+                if let Some(hashed) = HASH.borrow_mut().get_mut(name) {
+                    hashed.hits += 1;
+                }
+                return Ok(proc);
+            }
+            // The command is no longer where it was found, so it is searched for again
+            HASH.borrow_mut().remove(name);
+        }
+
+        let mut res = DOES_NOT_EXIST;
+        for dir in dirs {
+            match create(dir.hdl) {
+                Ok(proc) => {
+                    let hashed = Hashed {
+                        dir: dir.path.clone(),
+                        hits: 1,
+                    };
+                    HASH.borrow_mut().insert(String::from(name), hashed);
+                    return Ok(proc);
+                }
+                Err(e) => res = e,
+            }
+        }
+        Err(io::Error::from_raw_os_error(res))
+    })
+}
+
+/// The `hash` builtin, which lists the remembered commands, remembers the named commands, or forgets all of them with `-r`
+pub fn hash(args: &[String]) -> io::Result<i32> {
+    match args {
+        [] => {
+            let table = HASH.borrow();
+            if table.is_empty() {
+                println!("hash: hash table empty");
+                return Ok(0);
+            }
+            println!("hits\tcommand");
+            for (name, hashed) in table.iter() {
+                println!("{:4}\t{}", hashed.hits, join(&hashed.dir, name));
+            }
+            Ok(0)
+        }
+        [opt] if opt == "-r" => {
+            HASH.borrow_mut().clear();
+            Ok(0)
+        }
+        names => {
+            let mut status = 0;
+            for name in names {
+                if name.starts_with('-') {
+                    return Err(io::Error::new_with_message(
+                        io::ErrorKind::InvalidInput,
+                        "hash: usage: hash [-r] [name...]",
+                    ));
+                }
+                if BUILTINS.contains(&&**name) || name.contains('/') {
+                    continue;
+                }
+                match search(name, false).pop() {
+                    Some(dir) => {
+                        let hashed = Hashed { dir, hits: 0 };
+                        HASH.borrow_mut().insert(name.clone(), hashed);
+                    }
+                    None => {
+                        eprintln!("hash: {name}: not found");
+                        status = 1;
+                    }
+                }
+            }
+            Ok(status)
+        }
+    }
+}
+
+/// What a command name refers to
+enum Kind {
+    Alias(String),
+    Keyword,
+    Builtin,
+    File(String),
+}
+
+/// Finds what `name` refers to, in the order that the shell looks for it.
+///
+/// Unless `all` is set, only the first is returned, which is what runs.
+fn kinds(name: &str, all: bool) -> Vec<Kind> {
+    let mut kinds = Vec::new();
+    if let Some(value) = alias::get(name) {
+        kinds.push(Kind::Alias(value));
+    }
+    if KEYWORDS.contains(&name) {
+        kinds.push(Kind::Keyword);
+    }
+    if BUILTINS.contains(&name) {
+        kinds.push(Kind::Builtin);
+    }
+    if all && !name.contains('/') {
+        kinds.extend(
+            search(name, true)
+                .iter()
+                .map(|dir| Kind::File(join(dir, name))),
+        );
+    } else if let Some(path) = find(name) {
+        kinds.push(Kind::File(path));
+    }
+    if !all {
+        kinds.truncate(1);
+    }
+    kinds
+}
+
+fn describe(name: &str, kind: &Kind) {
+    match kind {
+        Kind::Alias(value) => println!("{name} is aliased to `{value}'"),
+        Kind::Keyword => println!("{name} is a shell keyword"),
+        Kind::Builtin => println!("{name} is a shell builtin"),
+        Kind::File(path) if HASH.borrow().contains_key(name) => {
+            println!("{name} is hashed ({path})")
+        }
+        Kind::File(path) => println!("{name} is {path}"),
+    }
+}
+
+/// The `type` builtin: `type [-a] [-t | -p] name...`.
+///
+/// Reports whether each name is an alias, a keyword, a builtin, or a file, and where the file is.
+/// `-t` prints only the kind, `-p` prints only the path of files, and `-a` reports every match rather than just the first.
+pub fn type_(args: &[String]) -> io::Result<i32> {
+    let (mut all, mut terse, mut path_only) = (false, false, false);
+    let mut names = args;
+    while let [opt, rest @ ..] = names
+        && let Some(flags) = opt.strip_prefix('-').filter(|flags| !flags.is_empty())
+    {
+        names = rest;
+        if flags == "-" {
+            break;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'a' => all = true,
+                't' => terse = true,
+                'p' => path_only = true,
+                _ => {
+                    return Err(io::Error::new_with_message(
+                        io::ErrorKind::InvalidInput,
+                        "type: usage: type [-a] [-t | -p] name...",
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut status = 0;
+    for name in names {
+        let kinds = kinds(name, all);
+        if kinds.is_empty() {
+            if !terse && !path_only {
+                eprintln!("type: {name}: not found");
+            }
+            status = 1;
+        }
+        for kind in &kinds {
+            match kind {
+                _ if terse => println!(
+                    "{}",
+                    match kind {
+                        Kind::Alias(_) => "alias",
+                        Kind::Keyword => "keyword",
+                        Kind::Builtin => "builtin",
+                        Kind::File(_) => "file",
+                    }
+                ),
+                Kind::File(path) if path_only => println!("{path}"),
+                _ if path_only => {}
+                kind => describe(name, kind),
+            }
+        }
+    }
+    Ok(status)
+}
+
+/// `command -v` or `command -V` (if `verbose` is set), which report how each name would be run, like `type`.
+///
+/// `-v` prints the path of files, the definition of aliases, and just the name of keywords and builtins.
+pub fn command_v(names: &[String], verbose: bool) -> io::Result<i32> {
+    let mut status = 0;
+    for name in names {
+        match kinds(name, false).first() {
+            Some(kind) if verbose => describe(name, kind),
+            Some(Kind::Alias(value)) => alias::print(name, value),
+            Some(Kind::File(path)) => println!("{path}"),
+            Some(_) => println!("{name}"),
+            None => {
+                if verbose {
+                    eprintln!("command: {name}: not found");
+                }
+                status = 1;
+            }
+        }
+    }
+    Ok(status)
+}
+
+/// The `which` builtin, which prints where each command is found in `PATH`, or every place with `-a`
+pub fn which(args: &[String]) -> io::Result<i32> {
+    let (all, names) = match args {
+        [opt, names @ ..] if opt == "-a" => (true, names),
+        names => (false, names),
+    };
+
+    let mut status = 0;
+    for name in names {
+        let mut found = false;
+        if name.contains('/') {
+            found = io::metadata(name).is_ok();
+            if found {
+                println!("{name}");
+            }
+        } else {
+            for dir in search(name, all) {
+                found = true;
+                println!("{}", join(&dir, name));
+            }
+        }
+        if !found {
+            status = 1;
+        }
+    }
+    Ok(status)
+}
@@ -0,0 +1,64 @@
+use alloc::vec::Vec;
+
+use super::bracket::Bracket;
+
+/// A shell pattern, as used by `[[ ... == pattern ]]`.
+///
+/// Each character remembers whether it was quoted, since quoted characters only match themselves.
+pub struct Pattern {
+    chars: Vec<(char, bool)>,
+}
+
+impl Pattern {
+    pub fn new() -> Self {
+        Self { chars: Vec::new() }
+    }
+
+    pub fn push_str(&mut self, s: &str, quoted: bool) {
+        self.chars.extend(s.chars().map(|c| (c, quoted)));
+    }
+
+    /// Whether the whole of `s` matches the pattern
+    pub fn matches(&self, s: &str) -> bool {
+        let s = s.chars().collect::<Vec<_>>();
+        matches_at(&self.chars, &s)
+    }
+}
+
+fn matches_at(pat: &[(char, bool)], s: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the last `*`, if the rest of the pattern fails to match
+    let mut star = None;
+    while n < s.len() {
+        let step = match pat.get(p) {
+            Some(('*', false)) => {
+                star = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some(('?', false)) => Some(1),
+            Some(('[', false)) => match Bracket::parse(pat[p + 1..].iter().copied(), &['!', '^']) {
+                Ok((bracket, rest)) => bracket.matches(s[n]).then_some(pat.len() - p - rest.len()),
+                // A `[` that doesn't start a bracket expression, such as an unterminated one, matches itself
+                Err(_) => (s[n] == '[').then_some(1),
+            },
+            Some(&(c, _)) => (s[n] == c).then_some(1),
+            None => None,
+        };
+        match step {
+            Some(len) => {
+                p += len;
+                n += 1;
+            }
+            None => match star {
+                Some((sp, sn)) => {
+                    p = sp;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pat[p..].iter().all(|&pc| pc == ('*', false))
+}
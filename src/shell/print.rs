@@ -0,0 +1,412 @@
+use alloc::{format, string::String, vec::Vec};
+use genio::Write as _;
+
+use super::{
+    escape::{self, Dialect},
+    quote::quote,
+    vars,
+};
+use crate::{eprintln, io};
+
+fn write_stdout(out: &[u8]) -> io::Result<()> {
+    io::with_stdout(|stdout| stdout.write_all(out))
+}
+
+/// The `echo` builtin.
+///
+/// Leading `-n`, `-e` and `-E` options suppress the trailing newline and enable or disable escapes.
+pub fn echo(args: &[String]) -> io::Result<i32> {
+    let mut newline = true;
+    let mut escapes = false;
+
+    let mut args = args;
+    while let Some((arg, rest)) = args.split_first() {
+        let Some(opts) = arg.strip_prefix('-') else {
+            break;
+        };
+        if opts.is_empty() || !opts.chars().all(|c| matches!(c, 'n' | 'e' | 'E')) {
+            break;
+        }
+        for c in opts.chars() {
+            match c {
+                'n' => newline = false,
+                'e' => escapes = true,
+                _ => escapes = false,
+            }
+        }
+        args = rest;
+    }
+
+    let mut out = Vec::new();
+    for (n, arg) in args.iter().enumerate() {
+        if n > 0 {
+            out.push(b' ');
+        }
+        if escapes {
+            let (bytes, stop) = escape::unescape(arg, Dialect::Echo);
+            out.extend_from_slice(&bytes);
+            // `\c` ends the output, including the newline
+            if stop {
+                write_stdout(&out)?;
+                return Ok(0);
+            }
+        } else {
+            out.extend_from_slice(&io::encode_bytes(arg));
+        }
+    }
+    if newline {
+        out.push(b'\n');
+    }
+    write_stdout(&out)?;
+    Ok(0)
+}
+
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alt: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    /// Writes `prefix` (a sign or radix prefix) and `body`, padded to the field width
+    fn pad(&self, out: &mut Vec<u8>, prefix: &str, body: &str, numeric: bool) {
+        let len = prefix.chars().count() + body.chars().count();
+        let fill = self.width.saturating_sub(len);
+        let body = io::encode_bytes(body);
+        if self.left {
+            out.extend_from_slice(prefix.as_bytes());
+            out.extend_from_slice(&body);
+            out.resize(out.len() + fill, b' ');
+        } else if self.zero && numeric {
+            out.extend_from_slice(prefix.as_bytes());
+            out.resize(out.len() + fill, b'0');
+            out.extend_from_slice(&body);
+        } else {
+            out.resize(out.len() + fill, b' ');
+            out.extend_from_slice(prefix.as_bytes());
+            out.extend_from_slice(&body);
+        }
+    }
+}
+
+/// The argument list of `printf`, which supplies empty strings once it runs out
+struct Args<'a> {
+    args: &'a [String],
+    pos: usize,
+    /// Set if an argument could not be converted to the number its conversion expected
+    invalid: bool,
+}
+
+impl<'a> Args<'a> {
+    fn next(&mut self) -> &'a str {
+        let arg = self.args.get(self.pos).map_or("", |arg| &**arg);
+        self.pos += 1;
+        arg
+    }
+
+    fn next_int(&mut self) -> i64 {
+        let arg = self.next();
+        parse_int(arg).unwrap_or_else(|| {
+            eprintln!("printf: {}: invalid number", quote(arg));
+            self.invalid = true;
+            0
+        })
+    }
+
+    fn next_float(&mut self) -> f64 {
+        let arg = self.next();
+        let val = match char_code(arg) {
+            Some(code) => Some(code as f64),
+            None if arg.trim().is_empty() => Some(0.0),
+            None => arg.trim().parse().ok(),
+        };
+        val.unwrap_or_else(|| {
+            eprintln!("printf: {}: invalid number", quote(arg));
+            self.invalid = true;
+            0.0
+        })
+    }
+}
+
+/// A numeric argument written as `'c` or `"c`, which is the code of the character `c`
+fn char_code(arg: &str) -> Option<u32> {
+    let rest = arg.strip_prefix(['\'', '"'])?;
+    Some(rest.chars().next().map_or(0, |c| c as u32))
+}
+
+/// Parses an integer argument, which may be decimal, octal with a leading `0`, hexadecimal with a leading `0x`, or a character code
+fn parse_int(arg: &str) -> Option<i64> {
+    if let Some(code) = char_code(arg) {
+        return Some(code as i64);
+    }
+
+    let arg = arg.trim();
+    if arg.is_empty() {
+        return Some(0);
+    }
+    let (neg, digits) = match arg.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, arg.strip_prefix('+').unwrap_or(arg)),
+    };
+    let (radix, digits) = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        (16, hex)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        (8, &digits[1..])
+    } else {
+        (10, digits)
+    };
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+
+    let val = u64::from_str_radix(digits, radix).ok()?;
+    Some(if neg {
+        (val as i64).wrapping_neg()
+    } else {
+        val as i64
+    })
+}
+
+fn format_int(out: &mut Vec<u8>, spec: &Spec, conv: char, val: i64) {
+    let (sign, mut digits) = match conv {
+        'd' | 'i' => {
+            let sign = if val < 0 {
+                "-"
+            } else if spec.plus {
+                "+"
+            } else if spec.space {
+                " "
+            } else {
+                ""
+            };
+            (sign, format!("{}", val.unsigned_abs()))
+        }
+        'u' => ("", format!("{}", val as u64)),
+        'o' => ("", format!("{:o}", val as u64)),
+        'x' => ("", format!("{:x}", val as u64)),
+        _ => ("", format!("{:X}", val as u64)),
+    };
+
+    if let Some(precision) = spec.precision {
+        if precision == 0 && val == 0 {
+            digits.clear();
+        } else if digits.len() < precision {
+            digits.insert_str(0, &"0".repeat(precision - digits.len()));
+        }
+    }
+
+    let prefix = match conv {
+        'o' if spec.alt && !digits.starts_with('0') => "0",
+        'x' if spec.alt && val != 0 => "0x",
+        'X' if spec.alt && val != 0 => "0X",
+        _ => sign,
+    };
+    // A precision gives the minimum number of digits, which replaces zero padding
+    let numeric = spec.precision.is_none();
+    spec.pad(out, prefix, &digits, numeric);
+}
+
+fn format_float(out: &mut Vec<u8>, spec: &Spec, conv: char, val: f64) {
+    let sign = if val.is_sign_negative() && !val.is_nan() {
+        "-"
+    } else if spec.plus {
+        "+"
+    } else if spec.space {
+        " "
+    } else {
+        ""
+    };
+
+    let val = val.abs();
+    let mut body = if val.is_nan() {
+        String::from("nan")
+    } else if val.is_infinite() {
+        String::from("inf")
+    } else {
+        let precision = spec.precision.unwrap_or(6);
+        let mut body = format!("{val:.precision$}");
+        if spec.alt && precision == 0 {
+            body.push('.');
+        }
+        body
+    };
+    if conv == 'F' {
+        body.make_ascii_uppercase();
+    }
+    spec.pad(out, sign, &body, val.is_finite());
+}
+
+/// Reads a field width or precision, which is either a number or `*` to take it from the next argument
+fn read_count(fmt: &mut &str, args: &mut Args) -> Option<i64> {
+    if let Some(rest) = fmt.strip_prefix('*') {
+        *fmt = rest;
+        return Some(args.next_int());
+    }
+    let len = fmt
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(fmt.len());
+    let (digits, rest) = fmt.split_at(len);
+    *fmt = rest;
+    digits.parse().ok()
+}
+
+/// The result of formatting the format string once
+enum Formatted {
+    Done,
+    /// A `\c` in a `%b` argument, which ends all output
+    Stop,
+}
+
+fn format_once(out: &mut Vec<u8>, format: &str, args: &mut Args) -> io::Result<Formatted> {
+    let mut fmt = format;
+    loop {
+        let n = fmt.find('%').unwrap_or(fmt.len());
+        out.extend_from_slice(&escape::unescape(&fmt[..n], Dialect::Printf).0);
+        fmt = &fmt[n..];
+
+        let Some(rest) = fmt.strip_prefix('%') else {
+            break;
+        };
+        fmt = rest;
+        if let Some(rest) = fmt.strip_prefix('%') {
+            out.push(b'%');
+            fmt = rest;
+            continue;
+        }
+
+        let mut spec = Spec::default();
+        loop {
+            match fmt.chars().next() {
+                Some('-') => spec.left = true,
+                Some('+') => spec.plus = true,
+                Some(' ') => spec.space = true,
+                Some('#') => spec.alt = true,
+                Some('0') => spec.zero = true,
+                _ => break,
+            }
+            fmt = &fmt[1..];
+        }
+        if let Some(width) = read_count(&mut fmt, args) {
+            // A negative width from an argument left-justifies the field
+            if width < 0 {
+                spec.left = true;
+            }
+            spec.width = width.unsigned_abs().min(u16::MAX as u64) as usize;
+        }
+        if let Some(rest) = fmt.strip_prefix('.') {
+            fmt = rest;
+            // A negative precision from an argument is ignored
+            spec.precision = match read_count(&mut fmt, args) {
+                Some(precision) if precision < 0 => None,
+                precision => Some(precision.unwrap_or(0).min(u16::MAX as i64) as usize),
+            };
+        }
+
+        let Some(conv) = fmt.chars().next() else {
+            return Err(io::Error::new_with_message(
+                io::ErrorKind::InvalidInput,
+                "printf: missing format character",
+            ));
+        };
+        fmt = &fmt[conv.len_utf8()..];
+
+        match conv {
+            's' => {
+                let arg = args.next();
+                let arg = match spec.precision {
+                    Some(precision) => arg
+                        .char_indices()
+                        .nth(precision)
+                        .map_or(arg, |(n, _)| &arg[..n]),
+                    None => arg,
+                };
+                spec.pad(out, "", arg, false);
+            }
+            'b' => {
+                let (bytes, stop) = escape::unescape(args.next(), Dialect::Echo);
+                let arg = io::decode_bytes(&bytes);
+                spec.pad(out, "", &arg, false);
+                if stop {
+                    return Ok(Formatted::Stop);
+                }
+            }
+            'q' => spec.pad(out, "", &quote(args.next()), false),
+            'c' => {
+                let arg = args.next();
+                let c = arg.chars().next().map_or("", |c| &arg[..c.len_utf8()]);
+                spec.pad(out, "", c, false);
+            }
+            'd' | 'i' | 'u' | 'o' | 'x' | 'X' => format_int(out, &spec, conv, args.next_int()),
+            'f' | 'F' => format_float(out, &spec, conv, args.next_float()),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("printf: `{conv}`: invalid format character"),
+                ));
+            }
+        }
+    }
+    Ok(Formatted::Done)
+}
+
+/// The `printf` builtin: `printf [-v var] format [arguments...]`.
+///
+/// The format is reused until all of the arguments are consumed.
+pub fn printf(args: &[String]) -> io::Result<i32> {
+    let mut args = args;
+    let mut var = None;
+    if let [opt, name, rest @ ..] = args
+        && opt == "-v"
+    {
+        if !vars::is_valid_name(name) {
+            return Err(io::Error::new_with_message(
+                io::ErrorKind::InvalidInput,
+                "printf: -v: invalid variable name",
+            ));
+        }
+        var = Some(name);
+        args = rest;
+    }
+    if let [opt, rest @ ..] = args
+        && opt == "--"
+    {
+        args = rest;
+    }
+    let Some((format, args)) = args.split_first() else {
+        return Err(io::Error::new_with_message(
+            io::ErrorKind::InvalidInput,
+            "printf: usage: printf [-v var] format [arguments]",
+        ));
+    };
+
+    let mut out = Vec::new();
+    let mut args = Args {
+        args,
+        pos: 0,
+        invalid: false,
+    };
+    loop {
+        let start = args.pos;
+        if let Formatted::Stop = format_once(&mut out, format, &mut args)? {
+            break;
+        }
+        // Stop once every argument is used, or if the format doesn't use any
+        if args.pos >= args.args.len() || args.pos == start {
+            break;
+        }
+    }
+
+    match var {
+        Some(name) => vars::set(name, io::decode_bytes(&out)),
+        None => write_stdout(&out)?,
+    }
+    Ok(args.invalid as i32)
+}
@@ -0,0 +1,151 @@
+use alloc::{borrow::Cow, string::String};
+use core::fmt::Write as _;
+
+use super::{parse::KEYWORDS, vars};
+use crate::io;
+
+/// Characters that have no special meaning anywhere in an unquoted word
+fn is_safe(c: char) -> bool {
+    match c {
+        c if c.is_ascii() => {
+            c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | ':' | ',' | '+' | '@' | '%' | '^' | '=')
+        }
+        c => !c.is_whitespace() && !c.is_control() && io::raw_byte(c).is_none(),
+    }
+}
+
+/// Characters that can only be written inside `$'...'`, which are control characters and bytes that aren't valid UTF-8
+fn needs_ansi_c(c: char) -> bool {
+    c.is_control() || io::raw_byte(c).is_some()
+}
+
+/// Whether `s` could be written as-is, and still be read back as a single word with the same value in any position
+fn is_bare(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars().all(is_safe)
+        && !KEYWORDS.contains(&s)
+        && !s.split_once('=').is_some_and(|(name, _)| vars::is_valid_name(name))
+}
+
+/// Quotes `s` so that it is read back by [`split_shell`][super::split_shell] as one word with the value `s`.
+///
+/// The shortest of the possible forms is used, and a string that needs no quoting is returned unchanged.
+pub fn quote(s: &str) -> Cow<'_, str> {
+    if is_bare(s) {
+        return Cow::Borrowed(s);
+    }
+    if s.is_empty() {
+        return Cow::Borrowed("''");
+    }
+    if s.chars().any(needs_ansi_c) {
+        return Cow::Owned(ansi_c(s));
+    }
+
+    Cow::Owned(
+        [single_quoted(s), double_quoted(s), escaped(s)]
+            .into_iter()
+            .min_by_key(String::len)
+            .unwrap(),
+    )
+}
+
+/// Quotes every character of `s`, so that it can be written next to other parts of a word without changing their meaning
+pub(crate) fn quote_all(s: &str) -> String {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => String::from("''"),
+        _ if s.chars().any(needs_ansi_c) => ansi_c(s),
+        (Some(c), None) => {
+            let mut out = String::from("\\");
+            out.push(c);
+            out
+        }
+        _ => {
+            let single = single_quoted(s);
+            let double = double_quoted(s);
+            if double.len() < single.len() {
+                double
+            } else {
+                single
+            }
+        }
+    }
+}
+
+/// `'...'`, with each `'` written as `'\''`
+pub(crate) fn single_quoted(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('\'');
+    for c in s.chars() {
+        match c {
+            '\'' => out.push_str("'\\''"),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// `"..."`, with the characters that are special inside double quotes escaped
+pub(crate) fn double_quoted(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        if matches!(c, '$' | '`' | '"' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
+
+/// Every unsafe character escaped with a `\`, or the first character if none are unsafe
+fn escaped(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() * 2);
+    for c in s.chars() {
+        if !is_safe(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    if out.len() == s.len() {
+        out.insert(0, '\\');
+    }
+    out
+}
+
+/// `$'...'`, which can represent control characters
+fn ansi_c(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 3);
+    out.push_str("$'");
+    for c in s.chars() {
+        if let Some(b) = io::raw_byte(c) {
+            let _ = write!(out, "\\x{b:02x}");
+            continue;
+        }
+        match c {
+            '\x07' => out.push_str("\\a"),
+            '\x08' => out.push_str("\\b"),
+            '\x1b' => out.push_str("\\e"),
+            '\x0c' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\x0b' => out.push_str("\\v"),
+            '\\' | '\'' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_ascii_control() => {
+                let _ = write!(out, "\\x{:02x}", c as u32);
+            }
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
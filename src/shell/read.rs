@@ -0,0 +1,329 @@
+use alloc::{string::String, vec::Vec};
+use core::time::Duration;
+use genio::{Read, bufio::BufRead};
+use lilium_sys::sys::{
+    thread::SleepThread,
+    time::{self, CLOCK_MONOTONIC, GetClockOffset},
+};
+
+use super::vars;
+use crate::{
+    eprint,
+    io::{self, BufReadEx, ByteReader, Stdio},
+};
+
+const DEFAULT_IFS: &str = " \t\n";
+
+const USAGE: &str =
+    "read: usage: read [-r] [-p prompt] [-t timeout] [-n count] [-d delim] [-a array] [name...]";
+
+/// The status of `read` when the timeout given by `-t` expires, as for a process killed by `SIGALRM`
+const TIMEOUT_STATUS: i32 = 128 + 14;
+
+/// How long `read -t` sleeps between checks for input
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+fn usage(msg: &'static str) -> io::Error {
+    io::Error::new_with_message(io::ErrorKind::InvalidInput, msg)
+}
+
+fn monotonic_time() -> Duration {
+    let mut now = bytemuck::zeroed();
+    unsafe {
+        GetClockOffset(CLOCK_MONOTONIC, &mut now);
+    }
+    Duration::new(now.seconds.max(0) as u64, now.nanos_of_second)
+}
+
+fn sleep(duration: Duration) {
+    let duration = time::Duration {
+        seconds: duration.as_secs() as i64,
+        nanos_of_second: duration.subsec_nanos(),
+    };
+    unsafe {
+        SleepThread(duration);
+    }
+}
+
+/// Reads stdin without waiting past a deadline
+struct Timeout {
+    stdin: Stdio,
+    deadline: Duration,
+}
+
+impl Read for Timeout {
+    type ReadError = io::Error;
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.stdin.read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    let now = monotonic_time();
+                    if now >= self.deadline {
+                        return Err(io::Error::new_with_message(
+                            io::ErrorKind::TimedOut,
+                            "read: timed out waiting for input",
+                        ));
+                    }
+                    sleep(POLL_INTERVAL.min(self.deadline - now));
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+struct Options {
+    raw: bool,
+    delim: u8,
+    /// The most characters to read, from `-n`
+    count: Option<usize>,
+    timeout: Option<Duration>,
+    array: Option<String>,
+}
+
+/// Whether `buf` ends with a `\` that isn't itself escaped
+fn ends_with_escape(buf: &[u8]) -> bool {
+    buf.iter().rev().take_while(|&&b| b == b'\\').count() % 2 == 1
+}
+
+/// Reads up to the delimiter, which is not included.
+///
+/// Unless `raw` is set, a `\` before a newline continues the input onto the next line.
+/// Returns whether the input ended before the delimiter was found.
+fn read_delimited<R: BufRead<ReadError = io::Error>>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    opts: &Options,
+) -> io::Result<bool> {
+    loop {
+        let start = buf.len();
+        if reader.read_until(buf, opts.delim)? == 0 || buf.last() != Some(&opts.delim) {
+            return Ok(true);
+        }
+        buf.pop();
+        if opts.raw || opts.delim != b'\n' || !ends_with_escape(&buf[start..]) {
+            return Ok(false);
+        }
+        buf.pop();
+    }
+}
+
+/// Reads up to `count` characters, stopping early at the delimiter.
+///
+/// Returns whether the input ended first.
+fn read_chars<R: BufRead<ReadError = io::Error>>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    count: usize,
+    opts: &Options,
+) -> io::Result<bool> {
+    let mut chars = 0;
+    // The continuation bytes still needed to finish the last character
+    let mut pending = 0;
+    while chars < count || pending > 0 {
+        let Some(&byte) = reader.fill_buf()?.first() else {
+            return Ok(true);
+        };
+        reader.consume(1);
+
+        if pending > 0 {
+            buf.push(byte);
+            pending -= 1;
+            continue;
+        }
+        if byte == opts.delim {
+            if !opts.raw && opts.delim == b'\n' && ends_with_escape(buf) {
+                buf.pop();
+                continue;
+            }
+            return Ok(false);
+        }
+        buf.push(byte);
+        chars += 1;
+        pending = match byte {
+            0xc0..=0xdf => 1,
+            0xe0..=0xef => 2,
+            0xf0..=0xf7 => 3,
+            _ => 0,
+        };
+    }
+    Ok(false)
+}
+
+fn read_input<R: BufRead<ReadError = io::Error>>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    opts: &Options,
+) -> io::Result<bool> {
+    match opts.count {
+        Some(count) => read_chars(reader, buf, count, opts),
+        None => read_delimited(reader, buf, opts),
+    }
+}
+
+/// Removes the backslashes from `line`, returning each character and whether it was escaped
+fn unescape(line: &str, raw: bool) -> Vec<(char, bool)> {
+    let mut out = Vec::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if !raw => {
+                if let Some(c) = chars.next() {
+                    out.push((c, true));
+                }
+            }
+            c => out.push((c, false)),
+        }
+    }
+    out
+}
+
+/// Splits `line` into fields on `IFS`.
+///
+/// If `max` is given, the last field takes the rest of the line, with only trailing `IFS` whitespace removed.
+fn split_line(line: &[(char, bool)], ifs: &str, max: Option<usize>) -> Vec<String> {
+    let is_space = |&(c, escaped): &(char, bool)| !escaped && c.is_whitespace() && ifs.contains(c);
+    let is_delim = |&(c, escaped): &(char, bool)| !escaped && ifs.contains(c);
+
+    let mut fields = Vec::new();
+    let mut rest = line;
+    while let [first, ..] = rest
+        && is_space(first)
+    {
+        rest = &rest[1..];
+    }
+
+    while !rest.is_empty() {
+        if max.is_some_and(|max| fields.len() + 1 >= max) {
+            let len = rest.len() - rest.iter().rev().take_while(|c| is_space(c)).count();
+            fields.push(rest[..len].iter().map(|&(c, _)| c).collect());
+            break;
+        }
+
+        let len = rest.iter().position(is_delim).unwrap_or(rest.len());
+        fields.push(rest[..len].iter().map(|&(c, _)| c).collect());
+        rest = &rest[len..];
+
+        // A separator is any amount of `IFS` whitespace, around at most one other `IFS` character
+        let mut delim = false;
+        while let [first, ..] = rest {
+            if is_space(first) {
+                rest = &rest[1..];
+            } else if is_delim(first) && !delim {
+                delim = true;
+                rest = &rest[1..];
+            } else {
+                break;
+            }
+        }
+    }
+    fields
+}
+
+/// The `read` builtin: `read [-r] [-p prompt] [-t timeout] [-n count] [-d delim] [-a array] [name...]`.
+///
+/// Reads a line from stdin and splits it on `IFS` into the named variables, or into `REPLY` without splitting if there are none.
+/// Returns 1 if the input ended before the delimiter, even if part of a line was read.
+pub fn read(args: &[String]) -> io::Result<i32> {
+    let mut opts = Options {
+        raw: false,
+        delim: b'\n',
+        count: None,
+        timeout: None,
+        array: None,
+    };
+    let mut prompt = None;
+
+    let mut args = args.iter();
+    let mut names = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            names.extend(args.by_ref());
+            break;
+        }
+        let Some(flags) = arg.strip_prefix('-').filter(|flags| !flags.is_empty()) else {
+            names.push(arg);
+            names.extend(args.by_ref());
+            break;
+        };
+
+        for (n, flag) in flags.char_indices() {
+            if flag == 'r' {
+                opts.raw = true;
+                continue;
+            }
+            // The value of an option is the rest of the argument, or the next argument
+            let value = match &flags[n + flag.len_utf8()..] {
+                "" => args.next().ok_or(usage("read: option requires an argument"))?,
+                rest => rest,
+            };
+            match flag {
+                'p' => prompt = Some(value),
+                'd' => opts.delim = value.bytes().next().unwrap_or(0),
+                'n' => {
+                    let count = value.parse().map_err(|_| usage("read: -n: invalid count"))?;
+                    opts.count = Some(count);
+                }
+                't' => {
+                    let timeout = value
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                        .ok_or(usage("read: -t: invalid timeout"))?;
+                    opts.timeout = Some(timeout);
+                }
+                'a' => opts.array = Some(String::from(value)),
+                _ => return Err(usage(USAGE)),
+            }
+            break;
+        }
+    }
+    for name in opts.array.iter().chain(names.iter().copied()) {
+        if !vars::is_valid_name(name) {
+            return Err(usage("read: invalid variable name"));
+        }
+    }
+
+    if let Some(prompt) = prompt {
+        eprint!("{prompt}");
+        io::flush_output();
+    }
+
+    let mut buf = Vec::new();
+    let res = match opts.timeout {
+        Some(timeout) => {
+            let stdin = io::stdin();
+            stdin.set_blocking(false)?;
+            // A timeout too long to reach is the same as waiting forever
+            let deadline = monotonic_time().checked_add(timeout).unwrap_or(Duration::MAX);
+            let mut reader = ByteReader::new(Timeout { stdin, deadline });
+            let res = read_input(&mut reader, &mut buf, &opts);
+            io::stdin().set_blocking(true)?;
+            res
+        }
+        None => read_input(&mut ByteReader::new(io::stdin()), &mut buf, &opts),
+    };
+    let status = match res {
+        Ok(false) => 0,
+        Ok(true) => 1,
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => TIMEOUT_STATUS,
+        Err(e) => return Err(e),
+    };
+
+    let line = io::decode_bytes(&buf);
+    let line = unescape(&line, opts.raw);
+    let ifs = vars::get("IFS");
+    let ifs = ifs.as_deref().unwrap_or(DEFAULT_IFS);
+
+    if let Some(array) = &opts.array {
+        vars::set_array(array, split_line(&line, ifs, None));
+    } else if names.is_empty() {
+        vars::set("REPLY", line.iter().map(|&(c, _)| c).collect());
+    } else {
+        let mut fields = split_line(&line, ifs, Some(names.len())).into_iter();
+        for name in names {
+            vars::set(name, fields.next().unwrap_or_default());
+        }
+    }
+    Ok(status)
+}
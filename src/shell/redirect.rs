@@ -0,0 +1,70 @@
+use alloc::{rc::Rc, vec::Vec};
+
+use super::{Op, Redirect, tilde};
+use crate::io::{self, OpenHandle, OpenMode};
+
+/// The largest descriptor that can be redirected
+const MAX_FD: u32 = 1023;
+
+fn bad_fd() -> io::Error {
+    io::Error::new_with_message(io::ErrorKind::InvalidInput, "bad file descriptor")
+}
+
+/// The descriptors that were redirected, and what they referred to before
+pub struct Saved(Vec<(u32, Option<Rc<OpenHandle>>)>);
+
+impl Saved {
+    /// Puts the descriptors back the way they were before the redirections.
+    ///
+    /// This is done in reverse order, so that a descriptor redirected twice gets back its original handle.
+    pub fn restore(self) {
+        for (fd, hdl) in self.0.into_iter().rev() {
+            io::set_fd(fd, hdl);
+        }
+    }
+}
+
+fn apply_one(redirect: &Redirect) -> io::Result<Option<Rc<OpenHandle>>> {
+    if redirect.fd > MAX_FD {
+        return Err(bad_fd());
+    }
+    let mut target = redirect.target.clone();
+    tilde::expand_tilde(&mut target, false)?;
+    let target = target.expand()?;
+
+    let open = |mode| io::open_redirect(&target, mode).map(|hdl| Some(Rc::new(hdl)));
+    let hdl = match redirect.op {
+        Op::Less => open(OpenMode::Read)?,
+        Op::Great => open(OpenMode::Write)?,
+        Op::DGreat => open(OpenMode::Append)?,
+        Op::LessGreat => open(OpenMode::ReadWrite)?,
+        Op::GreatAnd | Op::LessAnd if target == "-" => None,
+        Op::GreatAnd | Op::LessAnd => {
+            let hdl = target
+                .parse()
+                .ok()
+                .and_then(io::get_fd)
+                .ok_or_else(bad_fd)?;
+            Some(hdl)
+        }
+        op => unreachable!("`{op}` is not a redirection"),
+    };
+    Ok(io::set_fd(redirect.fd, hdl))
+}
+
+/// Applies `redirects` to the shell's descriptors in order, returning what is needed to undo them.
+///
+/// If one of them fails, the ones before it are undone.
+pub fn apply(redirects: &[Redirect]) -> io::Result<Saved> {
+    let mut saved = Saved(Vec::new());
+    for redirect in redirects {
+        match apply_one(redirect) {
+            Ok(hdl) => saved.0.push((redirect.fd, hdl)),
+            Err(e) => {
+                saved.restore();
+                return Err(e);
+            }
+        }
+    }
+    Ok(saved)
+}
@@ -0,0 +1,493 @@
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use core::{ops::Range, str::Chars};
+use memchr::memchr;
+
+use super::bracket::{Bracket, BracketError, CLASSES, Item};
+
+/// Limit on the count of a bounded repetition such as `a{2,5}`, like POSIX's `RE_DUP_MAX`
+const MAX_REPEAT: u32 = 255;
+
+/// Limit on the number of instructions a regular expression compiles to, which repetitions can multiply
+const MAX_PROGRAM: usize = 1 << 16;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegexError {
+    UnmatchedParen,
+    UnmatchedBracket,
+    InvalidClass(String),
+    InvalidRange(char, char),
+    NothingToRepeat(char),
+    InvalidRepeat,
+    TrailingBackslash,
+    TooBig,
+}
+
+impl core::fmt::Display for RegexError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RegexError::UnmatchedParen => f.write_str("unmatched `(`"),
+            RegexError::UnmatchedBracket => f.write_str("unmatched `[`"),
+            RegexError::InvalidClass(name) => write!(f, "invalid character class `{name}`"),
+            RegexError::InvalidRange(lo, hi) => write!(f, "invalid range end `{lo}-{hi}`"),
+            RegexError::NothingToRepeat(c) => write!(f, "`{c}` has nothing to repeat"),
+            RegexError::InvalidRepeat => f.write_str("invalid repetition count"),
+            RegexError::TrailingBackslash => f.write_str("trailing backslash"),
+            RegexError::TooBig => f.write_str("regular expression too big"),
+        }
+    }
+}
+
+impl core::error::Error for RegexError {}
+
+type Result<T> = core::result::Result<T, RegexError>;
+
+/// The characters of a regular expression, none of which are quoted, as [`Bracket::parse`] takes them
+#[derive(Clone)]
+struct Unquoted<'a>(Chars<'a>);
+
+impl Iterator for Unquoted<'_> {
+    type Item = (char, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|c| (c, false))
+    }
+}
+
+enum Node {
+    Char(char),
+    Any,
+    Set(Bracket),
+    /// `^`, which only matches at the start of the string
+    Start,
+    /// `$`, which only matches at the end of the string
+    End,
+    Group(usize, Box<Node>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    /// A node repeated at least `min` times, and at most `max` times if it is bounded
+    Repeat(Box<Node>, u32, Option<u32>),
+}
+
+impl Node {
+    /// The character that every match must start with, if there is one
+    fn first_char(&self) -> Option<char> {
+        match self {
+            Node::Char(c) => Some(*c),
+            Node::Group(_, node) => node.first_char(),
+            Node::Concat(nodes) => nodes.first()?.first_char(),
+            Node::Repeat(node, min, _) if *min > 0 => node.first_char(),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: Chars<'a>,
+    groups: usize,
+    /// How many groups the parser is inside, since a `)` outside of any group is an ordinary character
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.clone().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_alt(&mut self) -> Result<Node> {
+        let mut alts = vec![self.parse_concat()?];
+        while self.eat('|') {
+            alts.push(self.parse_concat()?);
+        }
+        Ok(if alts.len() == 1 {
+            alts.pop().unwrap()
+        } else {
+            Node::Alt(alts)
+        })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node> {
+        let mut nodes = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some('|') => break,
+                Some(')') if self.depth > 0 => break,
+                _ => {}
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_repeats(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node> {
+        Ok(match self.chars.next().unwrap() {
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '(' => {
+                self.groups += 1;
+                let group = self.groups;
+                self.depth += 1;
+                let inner = self.parse_alt()?;
+                self.depth -= 1;
+                if !self.eat(')') {
+                    return Err(RegexError::UnmatchedParen);
+                }
+                Node::Group(group, Box::new(inner))
+            }
+            '[' => Node::Set(self.parse_set()?),
+            '\\' => Node::Char(self.chars.next().ok_or(RegexError::TrailingBackslash)?),
+            c @ ('*' | '+' | '?') => return Err(RegexError::NothingToRepeat(c)),
+            c => Node::Char(c),
+        })
+    }
+
+    /// Parses any repetition operators following `node`
+    fn parse_repeats(&mut self, mut node: Node) -> Result<Node> {
+        loop {
+            let (min, max) = match self.peek() {
+                Some('{') => match self.parse_bound()? {
+                    Some(bound) => bound,
+                    // A `{` that doesn't start a bound is an ordinary character
+                    None => return Ok(node),
+                },
+                Some(c @ ('*' | '+' | '?')) => {
+                    self.chars.next();
+                    match c {
+                        '*' => (0, None),
+                        '+' => (1, None),
+                        _ => (0, Some(1)),
+                    }
+                }
+                _ => return Ok(node),
+            };
+            node = Node::Repeat(Box::new(node), min, max);
+        }
+    }
+
+    /// Parses a bound like `{2}`, `{2,}` or `{2,5}`, leaving the input unchanged if there isn't one
+    fn parse_bound(&mut self) -> Result<Option<(u32, Option<u32>)>> {
+        let mut chars = self.chars.clone();
+        chars.next();
+        let rest = chars.as_str();
+        let Some(len) = rest.find('}') else {
+            return Ok(None);
+        };
+        let body = &rest[..len];
+        if body.is_empty() || !body.chars().all(|c| c.is_ascii_digit() || c == ',') {
+            return Ok(None);
+        }
+
+        let count = |s: &str| -> Result<Option<u32>> {
+            if s.is_empty() {
+                return Ok(None);
+            }
+            match s.parse() {
+                Ok(n) if n <= MAX_REPEAT => Ok(Some(n)),
+                _ => Err(RegexError::InvalidRepeat),
+            }
+        };
+        let bound = match body.split_once(',') {
+            None => {
+                let n = count(body)?;
+                (n.unwrap_or(0), n)
+            }
+            Some((min, max)) if !max.contains(',') => (count(min)?.unwrap_or(0), count(max)?),
+            Some(_) => return Ok(None),
+        };
+        if bound.1.is_some_and(|max| max < bound.0) {
+            return Err(RegexError::InvalidRepeat);
+        }
+
+        self.chars = rest[len + 1..].chars();
+        Ok(Some(bound))
+    }
+
+    /// Parses a bracket expression, after the opening `[`
+    fn parse_set(&mut self) -> Result<Bracket> {
+        let (set, rest) =
+            Bracket::parse(Unquoted(self.chars.clone()), &['^']).map_err(|e| match e {
+                BracketError::Unterminated => RegexError::UnmatchedBracket,
+                BracketError::InvalidElement(name) => RegexError::InvalidClass(name),
+            })?;
+        self.chars = rest.0;
+
+        for item in &set.items {
+            match item {
+                Item::Class(name) if !CLASSES.contains(&name.as_str()) => {
+                    return Err(RegexError::InvalidClass(name.clone()));
+                }
+                &Item::Range(lo, hi) if hi < lo => return Err(RegexError::InvalidRange(lo, hi)),
+                _ => {}
+            }
+        }
+        Ok(set)
+    }
+}
+
+#[derive(Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Set(Bracket),
+    Start,
+    End,
+    /// Records the current position in a capture slot
+    Save(usize),
+    /// Continues at both targets, preferring the first
+    Split(usize, usize),
+    Jmp(usize),
+    Match,
+}
+
+struct Compiler {
+    prog: Vec<Inst>,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> Result<usize> {
+        if self.prog.len() >= MAX_PROGRAM {
+            return Err(RegexError::TooBig);
+        }
+        self.prog.push(inst);
+        Ok(self.prog.len() - 1)
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<()> {
+        match node {
+            Node::Char(c) => self.emit(Inst::Char(*c)).map(drop),
+            Node::Any => self.emit(Inst::Any).map(drop),
+            Node::Set(set) => self.emit(Inst::Set(set.clone())).map(drop),
+            Node::Start => self.emit(Inst::Start).map(drop),
+            Node::End => self.emit(Inst::End).map(drop),
+            Node::Group(n, node) => {
+                self.emit(Inst::Save(2 * n))?;
+                self.compile(node)?;
+                self.emit(Inst::Save(2 * n + 1)).map(drop)
+            }
+            Node::Concat(nodes) => nodes.iter().try_for_each(|node| self.compile(node)),
+            Node::Alt(alts) => {
+                let mut jumps = Vec::new();
+                for (n, alt) in alts.iter().enumerate() {
+                    if n + 1 == alts.len() {
+                        self.compile(alt)?;
+                        break;
+                    }
+                    let split = self.emit(Inst::Split(0, 0))?;
+                    self.compile(alt)?;
+                    jumps.push(self.emit(Inst::Jmp(0))?);
+                    self.prog[split] = Inst::Split(split + 1, self.prog.len());
+                }
+                let end = self.prog.len();
+                for jump in jumps {
+                    self.prog[jump] = Inst::Jmp(end);
+                }
+                Ok(())
+            }
+            Node::Repeat(node, min, max) => {
+                for _ in 0..*min {
+                    self.compile(node)?;
+                }
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.compile(node)?;
+                        self.emit(Inst::Jmp(split))?;
+                        self.prog[split] = Inst::Split(split + 1, self.prog.len());
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.emit(Inst::Split(0, 0))?);
+                            self.compile(node)?;
+                        }
+                        let end = self.prog.len();
+                        for split in splits {
+                            self.prog[split] = Inst::Split(split + 1, end);
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The positions recorded by a thread, two for each group, where the whole match is group 0
+type Slots = Vec<Option<usize>>;
+
+/// The threads at one position in the input, in order of priority
+struct Threads {
+    threads: Vec<(usize, Slots)>,
+    /// The instructions that already have a thread, since a later thread at the same instruction can't do better
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self {
+            threads: Vec::new(),
+            seen: vec![false; len],
+        }
+    }
+}
+
+/// Escapes the characters of `s` that are special in a regular expression, so that it only matches itself
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(
+            c,
+            '\\' | '.' | '[' | ']' | '(' | ')' | '*' | '+' | '?' | '{' | '}' | '|' | '^' | '$'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// A POSIX extended regular expression.
+///
+/// Matching finds the leftmost match, and the longest one that starts there.
+/// Where the groups of that match could be split in several ways, the earlier alternatives and longer repetitions are preferred.
+pub struct Regex {
+    prog: Vec<Inst>,
+    groups: usize,
+    /// The first byte of every match, which lets the search skip ahead with `memchr`
+    first: Option<u8>,
+}
+
+impl Regex {
+    pub fn new(re: &str) -> Result<Self> {
+        let mut parser = Parser {
+            chars: re.chars(),
+            groups: 0,
+            depth: 0,
+        };
+        let node = parser.parse_alt()?;
+        debug_assert!(parser.peek().is_none());
+
+        let mut compiler = Compiler { prog: Vec::new() };
+        compiler.emit(Inst::Save(0))?;
+        compiler.compile(&node)?;
+        compiler.emit(Inst::Save(1))?;
+        compiler.emit(Inst::Match)?;
+
+        Ok(Self {
+            prog: compiler.prog,
+            groups: parser.groups,
+            first: node.first_char().map(|c| {
+                let mut buf = [0; 4];
+                c.encode_utf8(&mut buf);
+                buf[0]
+            }),
+        })
+    }
+
+    /// Finds the first match in `s`, returning the range of the whole match followed by the range of each group.
+    ///
+    /// A group that didn't take part in the match has no range.
+    pub fn captures(&self, s: &str) -> Option<Vec<Option<Range<usize>>>> {
+        let slots = self.search(s)?;
+        Some(
+            slots
+                .chunks(2)
+                .map(|pair| match *pair {
+                    [Some(start), Some(end)] => Some(start..end),
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+
+    /// Adds a thread at `pc`, following every instruction that doesn't consume input
+    fn add_thread(&self, list: &mut Threads, pc: usize, pos: usize, s: &str, slots: Slots) {
+        let mut stack = vec![(pc, slots)];
+        while let Some((pc, mut slots)) = stack.pop() {
+            if core::mem::replace(&mut list.seen[pc], true) {
+                continue;
+            }
+            match self.prog[pc] {
+                Inst::Jmp(to) => stack.push((to, slots)),
+                Inst::Split(first, second) => {
+                    stack.push((second, slots.clone()));
+                    stack.push((first, slots));
+                }
+                Inst::Save(slot) => {
+                    slots[slot] = Some(pos);
+                    stack.push((pc + 1, slots));
+                }
+                Inst::Start if pos == 0 => stack.push((pc + 1, slots)),
+                Inst::End if pos == s.len() => stack.push((pc + 1, slots)),
+                Inst::Start | Inst::End => {}
+                _ => list.threads.push((pc, slots)),
+            }
+        }
+    }
+
+    fn search(&self, s: &str) -> Option<Slots> {
+        let mut clist = Threads::new(self.prog.len());
+        let mut nlist = Threads::new(self.prog.len());
+        let mut best: Option<Slots> = None;
+        let mut pos = 0;
+        loop {
+            // A match that starts later can't be better than one that was already found
+            if best.is_none() {
+                if clist.threads.is_empty()
+                    && let Some(first) = self.first
+                {
+                    pos += memchr(first, &s.as_bytes()[pos..])?;
+                }
+                self.add_thread(&mut clist, 0, pos, s, vec![None; 2 * (self.groups + 1)]);
+            } else if clist.threads.is_empty() {
+                break;
+            }
+
+            let c = s[pos..].chars().next();
+            for (pc, slots) in clist.threads.drain(..) {
+                if let Some(best) = &best
+                    && slots[0] > best[0]
+                {
+                    continue;
+                }
+                let matched = match (&self.prog[pc], c) {
+                    (Inst::Match, _) => {
+                        // An earlier start, or a longer match from the same start
+                        let better = best
+                            .as_ref()
+                            .is_none_or(|best| slots[0] < best[0] || slots[1] > best[1]);
+                        if better {
+                            best = Some(slots);
+                        }
+                        continue;
+                    }
+                    (Inst::Char(x), Some(c)) => *x == c,
+                    (Inst::Any, Some(_)) => true,
+                    (Inst::Set(set), Some(c)) => set.matches(c),
+                    _ => false,
+                };
+                if matched && let Some(c) = c {
+                    self.add_thread(&mut nlist, pc + 1, pos + c.len_utf8(), s, slots);
+                }
+            }
+            clist.seen.fill(false);
+            core::mem::swap(&mut clist, &mut nlist);
+
+            match c {
+                Some(c) => pos += c.len_utf8(),
+                None => break,
+            }
+        }
+        best
+    }
+}
@@ -0,0 +1,262 @@
+use alloc::{format, string::String};
+use lilium_sys::sys::fs::{ACCESS_EXECUTE, ACCESS_READ, ACCESS_WRITE};
+
+use super::{CondExpr, Word, arith, quote::quote, regex::Regex, tilde, vars};
+use crate::{eprintln, io};
+
+/// Operators that test a single argument
+pub(crate) const UNARY_OPS: [&str; 11] = [
+    "-e", "-f", "-d", "-r", "-w", "-x", "-s", "-L", "-h", "-z", "-n",
+];
+
+/// Operators that compare two arguments
+pub(crate) const BINARY_OPS: [&str; 13] = [
+    "=", "==", "!=", "<", ">", "-eq", "-ne", "-lt", "-le", "-gt", "-ge", "-nt", "-ot",
+];
+
+fn is_unary(arg: &str) -> bool {
+    UNARY_OPS.contains(&arg)
+}
+
+fn is_binary(arg: &str) -> bool {
+    BINARY_OPS.contains(&arg)
+}
+
+/// Applies a unary operator to `arg`, which is a path for all but `-z` and `-n`
+fn unary(op: &str, arg: &str) -> bool {
+    match op {
+        "-z" => arg.is_empty(),
+        "-n" => !arg.is_empty(),
+        "-e" => io::metadata(arg).is_ok(),
+        "-f" => io::metadata(arg).is_ok_and(|meta| meta.is_file()),
+        "-d" => io::metadata(arg).is_ok_and(|meta| meta.is_dir()),
+        "-s" => io::metadata(arg).is_ok_and(|meta| meta.len() > 0),
+        "-L" | "-h" => io::symlink_metadata(arg).is_ok_and(|meta| meta.is_symlink()),
+        "-r" => io::access(arg, ACCESS_READ),
+        "-w" => io::access(arg, ACCESS_WRITE),
+        "-x" => io::access(arg, ACCESS_EXECUTE),
+        _ => unreachable!("unknown unary operator {op}"),
+    }
+}
+
+/// Applies a binary operator that compares strings or files
+fn binary(lhs: &str, op: &str, rhs: &str) -> bool {
+    match op {
+        "=" | "==" => lhs == rhs,
+        "!=" => lhs != rhs,
+        "<" => lhs < rhs,
+        ">" => lhs > rhs,
+        // A file that exists is newer than one that doesn't
+        "-nt" => match (io::metadata(lhs), io::metadata(rhs)) {
+            (Ok(lhs), Ok(rhs)) => lhs.modified() > rhs.modified(),
+            (lhs, _) => lhs.is_ok(),
+        },
+        "-ot" => match (io::metadata(lhs), io::metadata(rhs)) {
+            (Ok(lhs), Ok(rhs)) => lhs.modified() < rhs.modified(),
+            (_, rhs) => rhs.is_ok(),
+        },
+        _ => unreachable!("unknown binary operator {op}"),
+    }
+}
+
+/// Applies one of the integer comparison operators, such as `-eq`
+fn compare(lhs: i64, op: &str, rhs: i64) -> bool {
+    match op {
+        "-eq" => lhs == rhs,
+        "-ne" => lhs != rhs,
+        "-lt" => lhs < rhs,
+        "-le" => lhs <= rhs,
+        "-gt" => lhs > rhs,
+        "-ge" => lhs >= rhs,
+        _ => unreachable!("unknown integer operator {op}"),
+    }
+}
+
+fn is_int_op(op: &str) -> bool {
+    matches!(op, "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge")
+}
+
+fn parse_int(arg: &str) -> Result<i64, String> {
+    arg.trim()
+        .parse()
+        .map_err(|_| format!("{}: integer expression expected", quote(arg)))
+}
+
+fn test_binary(lhs: &str, op: &str, rhs: &str) -> Result<bool, String> {
+    if is_int_op(op) {
+        Ok(compare(parse_int(lhs)?, op, parse_int(rhs)?))
+    } else {
+        Ok(binary(lhs, op, rhs))
+    }
+}
+
+/// Evaluates the arguments of `test`.
+///
+/// Up to four arguments are interpreted by their count, so that an operand that looks like an operator (as in `test -n -a`) is still an operand.
+fn eval_args(args: &[&str]) -> Result<bool, String> {
+    match *args {
+        [] => Ok(false),
+        [arg] => Ok(!arg.is_empty()),
+        ["!", arg] => Ok(arg.is_empty()),
+        [op, arg] if is_unary(op) => Ok(unary(op, arg)),
+        [op, _] => Err(format!("{}: unary operator expected", quote(op))),
+        [lhs, op, rhs] if is_binary(op) => test_binary(lhs, op, rhs),
+        [lhs, "-a", rhs] => Ok(!lhs.is_empty() && !rhs.is_empty()),
+        [lhs, "-o", rhs] => Ok(!lhs.is_empty() || !rhs.is_empty()),
+        ["!", ..] if args.len() <= 4 => eval_args(&args[1..]).map(|val| !val),
+        ["(", arg, ")"] => Ok(!arg.is_empty()),
+        ["(", a, b, ")"] => eval_args(&[a, b]),
+        [_, op, _] => Err(format!("{}: binary operator expected", quote(op))),
+        _ => {
+            let mut parser = ArgParser { args, pos: 0 };
+            let val = parser.parse_or()?;
+            match parser.args.get(parser.pos) {
+                None => Ok(val),
+                Some(arg) => Err(format!("{}: unexpected argument", quote(arg))),
+            }
+        }
+    }
+}
+
+/// Parses the arguments of `test` with `!`, `-a`, `-o` and parentheses, when there are too many to interpret by their count
+struct ArgParser<'a, 'b> {
+    args: &'b [&'a str],
+    pos: usize,
+}
+
+impl<'a> ArgParser<'a, '_> {
+    fn next(&mut self) -> Result<&'a str, String> {
+        let arg = self
+            .args
+            .get(self.pos)
+            .ok_or_else(|| String::from("argument expected"))?;
+        self.pos += 1;
+        Ok(arg)
+    }
+
+    fn peek_is(&self, arg: &str) -> bool {
+        self.args.get(self.pos) == Some(&arg)
+    }
+
+    fn parse_or(&mut self) -> Result<bool, String> {
+        let mut val = self.parse_and()?;
+        while self.peek_is("-o") {
+            self.pos += 1;
+            // Both sides are parsed, even if the result is already known
+            val |= self.parse_and()?;
+        }
+        Ok(val)
+    }
+
+    fn parse_and(&mut self) -> Result<bool, String> {
+        let mut val = self.parse_not()?;
+        while self.peek_is("-a") {
+            self.pos += 1;
+            val &= self.parse_not()?;
+        }
+        Ok(val)
+    }
+
+    fn parse_not(&mut self) -> Result<bool, String> {
+        if self.peek_is("!") {
+            self.pos += 1;
+            return self.parse_not().map(|val| !val);
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<bool, String> {
+        let arg = self.next()?;
+        if arg == "(" {
+            let val = self.parse_or()?;
+            if self.next()? != ")" {
+                return Err(String::from("`)` expected"));
+            }
+            return Ok(val);
+        }
+        if is_unary(arg) && self.pos < self.args.len() {
+            return Ok(unary(arg, self.next()?));
+        }
+        if let Some(&op) = self.args.get(self.pos)
+            && is_binary(op)
+        {
+            self.pos += 1;
+            let rhs = self.next()?;
+            return test_binary(arg, op, rhs);
+        }
+        Ok(!arg.is_empty())
+    }
+}
+
+/// The `test` builtin, or `[` if `bracket` is set, which requires a closing `]` argument.
+///
+/// Returns 0 if the expression is true, 1 if it is false, and 2 if it is invalid.
+pub fn test(args: &[String], bracket: bool) -> io::Result<i32> {
+    let name = if bracket { "[" } else { "test" };
+    let mut args = args
+        .iter()
+        .map(|arg| &**arg)
+        .collect::<alloc::vec::Vec<_>>();
+    if bracket && args.pop() != Some("]") {
+        eprintln!("[: missing `]`");
+        return Ok(2);
+    }
+
+    match eval_args(&args) {
+        Ok(val) => Ok(!val as i32),
+        Err(e) => {
+            eprintln!("{name}: {e}");
+            Ok(2)
+        }
+    }
+}
+
+/// Expands a word of a `[[ ]]` command, which is not split into fields
+fn expand_word(word: &Word) -> io::Result<String> {
+    let mut word = word.clone();
+    tilde::expand_tilde(&mut word, false)?;
+    word.expand()
+}
+
+fn eval_int(word: &Word) -> io::Result<i64> {
+    arith::eval(&expand_word(word)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Evaluates the expression of a `[[ ]]` command.
+///
+/// The right side of `==` and `!=` is a pattern, the right side of `=~` is a regular expression, and the operands of the integer comparisons are arithmetic expressions.
+pub fn eval_cond(expr: &CondExpr) -> io::Result<bool> {
+    match expr {
+        CondExpr::Word(word) => Ok(!expand_word(word)?.is_empty()),
+        CondExpr::Unary(op, word) => Ok(unary(op, &expand_word(word)?)),
+        CondExpr::Binary(lhs, op, rhs) => match *op {
+            "=" | "==" | "!=" => {
+                let lhs = expand_word(lhs)?;
+                let mut rhs = rhs.clone();
+                tilde::expand_tilde(&mut rhs, false)?;
+                let matched = rhs.expand_pattern()?.matches(&lhs);
+                Ok(matched == (*op != "!="))
+            }
+            "=~" => {
+                let lhs = expand_word(lhs)?;
+                let re = Regex::new(&rhs.expand_regex()?)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                // `BASH_REMATCH` holds the match and each group, or nothing if there was no match
+                let captures = re.captures(&lhs);
+                let matched = captures.is_some();
+                let captures = captures
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|range| range.map_or_else(String::new, |range| lhs[range].into()))
+                    .collect();
+                vars::set_array("BASH_REMATCH", captures);
+                Ok(matched)
+            }
+            op if is_int_op(op) => Ok(compare(eval_int(lhs)?, op, eval_int(rhs)?)),
+            op => Ok(binary(&expand_word(lhs)?, op, &expand_word(rhs)?)),
+        },
+        CondExpr::Not(expr) => Ok(!eval_cond(expr)?),
+        CondExpr::And(lhs, rhs) => Ok(eval_cond(lhs)? && eval_cond(rhs)?),
+        CondExpr::Or(lhs, rhs) => Ok(eval_cond(lhs)? || eval_cond(rhs)?),
+    }
+}
@@ -0,0 +1,94 @@
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+use super::{
+    vars,
+    word::{Word, WordPart},
+};
+use crate::io;
+
+/// Looks up the directory named by a tilde-prefix (without the leading `~`).
+///
+/// Returns `None` if the prefix should be left as-is.
+fn lookup(name: &str) -> io::Result<Option<String>> {
+    match name {
+        "" => Ok(vars::get("HOME")),
+        "+" => Ok(vars::get("PWD")),
+        "-" => Ok(vars::get("OLDPWD")),
+        name if name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')) =>
+        {
+            // Lilium has no user database that we could resolve a login name against
+            Err(io::Error::new_with_message(
+                io::ErrorKind::Unsupported,
+                "~user: no user database is available to look up home directories",
+            ))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Performs tilde expansion on the unquoted start of `word`.
+///
+/// In an assignment value (`assignment` is set), a tilde following an unquoted `:` is also expanded, as in `PATH=~/bin:~/.local/bin`.
+/// The expanded directory is quoted, so it is not subject to any further expansion.
+pub fn expand_tilde(word: &mut Word, assignment: bool) -> io::Result<()> {
+    let needs_expansion = word.parts.iter().enumerate().any(|(n, part)| match part {
+        WordPart::Literal(lit) => (n == 0 && lit.starts_with('~')) || (assignment && lit.contains(":~")),
+        _ => false,
+    });
+    if !needs_expansion {
+        return Ok(());
+    }
+
+    let num_parts = word.parts.len();
+    let mut parts = Vec::with_capacity(num_parts);
+    for (n, part) in core::mem::take(&mut word.parts).into_iter().enumerate() {
+        let WordPart::Literal(lit) = part else {
+            parts.push(part);
+            continue;
+        };
+        let last = n + 1 == num_parts;
+
+        let starts = (n == 0)
+            .then_some(0)
+            .into_iter()
+            .chain(
+                lit.match_indices(':')
+                    .map(|(pos, _)| pos + 1)
+                    .filter(|_| assignment),
+            );
+
+        let mut copied = 0;
+        for start in starts {
+            if !lit[start..].starts_with('~') {
+                continue;
+            }
+            let end = lit[start..]
+                .find(|c| c == '/' || (assignment && c == ':'))
+                .map(|len| start + len);
+            // The tilde-prefix runs into the next part, so some of it is quoted
+            let Some(end) = end.or(last.then_some(lit.len())) else {
+                continue;
+            };
+
+            let Some(dir) = lookup(&lit[start + 1..end])? else {
+                continue;
+            };
+            if copied < start {
+                parts.push(WordPart::Literal(Cow::Owned(lit[copied..start].into())));
+            }
+            parts.push(WordPart::Quoted(Cow::Owned(dir)));
+            copied = end;
+        }
+
+        if copied == 0 {
+            parts.push(WordPart::Literal(lit));
+        } else if copied < lit.len() {
+            parts.push(WordPart::Literal(Cow::Owned(lit[copied..].into())));
+        }
+    }
+    word.parts = parts;
+
+    Ok(())
+}
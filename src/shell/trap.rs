@@ -0,0 +1,294 @@
+use core::cell::{Cell, LazyCell, RefCell};
+
+use alloc::{collections::BTreeMap, string::String};
+use lilium_sys::sys::except::{ExceptionStatusInfo, InstallExceptionHandler, SIGNAL};
+
+use super::{
+    LAST_STATUS, exec_list, expand_aliases, parse_shell, quote::single_quoted, split_shell,
+};
+use crate::{eprintln, io, println};
+
+/// The signals that can be trapped, and their numbers
+const SIGNALS: [(&str, u64); 10] = [
+    ("HUP", 1),
+    ("INT", 2),
+    ("QUIT", 3),
+    ("ABRT", 6),
+    ("USR1", 10),
+    ("USR2", 12),
+    ("PIPE", 13),
+    ("ALRM", 14),
+    ("TERM", 15),
+    ("CHLD", 17),
+];
+
+/// The number of `SIGINT`, which is delivered when Ctrl-C is pressed
+const SIGINT: u64 = 2;
+
+/// The status of a command that was stopped by Ctrl-C, as for a process killed by `SIGINT`
+pub const INTERRUPT_STATUS: i32 = 128 + SIGINT as i32;
+
+/// The conditions that the shell raises itself.
+///
+/// `RETURN` is accepted, but there are no functions or sourced scripts for it to run after yet.
+const CONDITIONS: [&str; 4] = ["EXIT", "ERR", "DEBUG", "RETURN"];
+
+/// The commands to run for each condition or signal, where an empty string ignores a signal
+#[thread_local]
+static TRAPS: LazyCell<RefCell<BTreeMap<&'static str, String>>> =
+    LazyCell::new(|| RefCell::new(BTreeMap::new()));
+
+/// The signals that have a trap, by number, which the exception handler can check without borrowing [`TRAPS`]
+#[thread_local]
+static TRAPPED: Cell<u64> = Cell::new(0);
+
+/// The trapped signals that were delivered, whose traps haven't run yet
+#[thread_local]
+static PENDING: Cell<u64> = Cell::new(0);
+
+/// Set when Ctrl-C is pressed without an `INT` trap, so that the rest of the line isn't run
+#[thread_local]
+static INTERRUPTED: Cell<bool> = Cell::new(false);
+
+/// Set while a trap is running, so that the `DEBUG` and `ERR` traps don't run for its commands
+#[thread_local]
+static IN_TRAP: Cell<bool> = Cell::new(false);
+
+/// How many `if`, `while` or `until` conditions are being run, where a failing command doesn't run the `ERR` trap
+#[thread_local]
+static CONDITION: Cell<u32> = Cell::new(0);
+
+/// Records a trapped signal so that its trap runs once the current command finishes.
+///
+/// Lilium delivers a POSIX signal as the [`SIGNAL`] exception, with the signal number in `except_info`.
+/// `SIGINT` is always handled, so that Ctrl-C stops the command being run rather than the shell.
+unsafe extern "C" fn handle_exception(info: *const ExceptionStatusInfo) -> bool {
+    let info = unsafe { &*info };
+    let sig = info.except_info;
+    if info.except_code != SIGNAL
+        || sig >= 64
+        || sig != SIGINT && TRAPPED.get() & 1 << sig == 0
+    {
+        return false;
+    }
+    PENDING.set(PENDING.get() | 1 << sig);
+    true
+}
+
+/// Installs the shell's handler for the signals that Lilium delivers as exceptions
+pub fn install_handler() -> io::Result<()> {
+    let res = unsafe { InstallExceptionHandler(handle_exception) };
+    if res < 0 {
+        Err(io::Error::from_raw_os_error(res))
+    } else {
+        Ok(())
+    }
+}
+
+/// Finds the name of a condition or signal, which may be given as a number, in lowercase, or with a `SIG` prefix
+fn condition(name: &str) -> Option<&'static str> {
+    if name == "0" {
+        return Some("EXIT");
+    }
+    if let Ok(num) = name.parse::<u64>() {
+        return SIGNALS
+            .iter()
+            .find(|&&(_, n)| n == num)
+            .map(|&(name, _)| name);
+    }
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    CONDITIONS
+        .into_iter()
+        .chain(SIGNALS.iter().map(|&(name, _)| name))
+        .find(|&cond| cond == name)
+}
+
+fn signal_number(name: &str) -> Option<u64> {
+    SIGNALS
+        .iter()
+        .find(|&&(sig, _)| sig == name)
+        .map(|&(_, n)| n)
+}
+
+fn set(name: &'static str, action: Option<&str>) {
+    let mut traps = TRAPS.borrow_mut();
+    match action {
+        Some(action) => {
+            traps.insert(name, String::from(action));
+        }
+        None => {
+            traps.remove(name);
+        }
+    }
+    drop(traps);
+
+    if let Some(num) = signal_number(name) {
+        let bit = 1 << num;
+        match action {
+            Some(_) => TRAPPED.set(TRAPPED.get() | bit),
+            None => TRAPPED.set(TRAPPED.get() & !bit),
+        }
+    }
+}
+
+fn print(name: &str, action: &str) {
+    println!("trap -- {} {name}", single_quoted(action));
+}
+
+/// The `trap` builtin: `trap [-lp] [[action] condition...]`.
+///
+/// Sets `action` to run when each condition happens, which is either a signal or one of `EXIT`, `ERR`, `DEBUG` and `RETURN`.
+/// An empty action ignores a signal, and an action of `-`, or none with a single condition, removes the trap.
+/// With no arguments, or with `-p`, the traps are printed as the commands that set them.
+pub fn trap(args: &[String]) -> io::Result<i32> {
+    let args = match args {
+        [opt, rest @ ..] if opt == "--" => rest,
+        args => args,
+    };
+    let (action, names) = match args {
+        [] => {
+            for (name, action) in TRAPS.borrow().iter() {
+                print(name, action);
+            }
+            return Ok(0);
+        }
+        [opt] if opt == "-l" => {
+            for (name, n) in SIGNALS {
+                println!("{n:2}) SIG{name}");
+            }
+            return Ok(0);
+        }
+        [opt, names @ ..] if opt == "-p" => {
+            let traps = TRAPS.borrow();
+            for (name, action) in traps.iter() {
+                if names.is_empty() || names.iter().any(|arg| condition(arg) == Some(name)) {
+                    print(name, action);
+                }
+            }
+            return Ok(0);
+        }
+        [name] => (None, core::slice::from_ref(name)),
+        [action, names @ ..] => (Some(&**action).filter(|&action| action != "-"), names),
+    };
+
+    let mut status = 0;
+    for arg in names {
+        match condition(arg) {
+            Some(name) => set(name, action),
+            None => {
+                eprintln!("trap: {arg}: invalid signal specification");
+                status = 1;
+            }
+        }
+    }
+    Ok(status)
+}
+
+/// Runs the commands of a trap, keeping `$?` as it was before
+fn exec_trap(cmds: &str) {
+    let status = LAST_STATUS.get();
+    let in_trap = IN_TRAP.replace(true);
+    match parse_shell(expand_aliases(split_shell(cmds))) {
+        Ok(list) => {
+            exec_list(&list);
+        }
+        Err(e) => eprintln!("trap: {e}"),
+    }
+    IN_TRAP.set(in_trap);
+    LAST_STATUS.set(status);
+}
+
+/// Runs the trap for `name`, unless another trap is already running
+fn run(name: &str) {
+    if IN_TRAP.get() {
+        return;
+    }
+    let Some(cmds) = TRAPS.borrow().get(name).cloned() else {
+        return;
+    };
+    if !cmds.is_empty() {
+        exec_trap(&cmds);
+    }
+}
+
+/// Runs the traps of the signals that were delivered since the last time this was called
+pub fn run_pending_traps() {
+    // Signals that arrive during a trap are kept until it finishes
+    if IN_TRAP.get() {
+        return;
+    }
+    let pending = PENDING.replace(0);
+    for (name, n) in SIGNALS {
+        if pending & 1 << n == 0 {
+            continue;
+        }
+        if n == SIGINT && !TRAPS.borrow().contains_key(name) {
+            INTERRUPTED.set(true);
+        } else {
+            run(name);
+        }
+    }
+}
+
+/// Whether Ctrl-C has been pressed since the last call to [`run_pending_traps`], such as while waiting for a program,
+/// and `SIGINT` isn't ignored by `trap '' INT`
+pub fn interrupt_pending() -> bool {
+    PENDING.get() & 1 << SIGINT != 0 && !ignored("INT")
+}
+
+/// Whether the signal `name` has an empty trap, which ignores it
+fn ignored(name: &str) -> bool {
+    TRAPS.borrow().get(name).is_some_and(|cmds| cmds.is_empty())
+}
+
+/// Whether Ctrl-C was pressed without an `INT` trap, so that the commands left on the line should be skipped
+pub fn interrupted() -> bool {
+    INTERRUPTED.get()
+}
+
+/// Clears and returns whether Ctrl-C was pressed without an `INT` trap, once the shell is back at the prompt
+pub fn take_interrupt() -> bool {
+    INTERRUPTED.replace(false)
+}
+
+/// Runs the `DEBUG` trap, before a simple command
+pub fn debug() {
+    run("DEBUG");
+}
+
+/// Runs the `ERR` trap after a command fails, unless it is part of a condition
+pub fn err(status: i32) {
+    if status != 0 && CONDITION.get() == 0 {
+        run("ERR");
+    }
+}
+
+/// Runs `f` as the condition of an `if`, `while` or `until`, where failing commands don't run the `ERR` trap
+pub fn condition_scope<R>(f: impl FnOnce() -> R) -> R {
+    CONDITION.set(CONDITION.get() + 1);
+    let res = f();
+    CONDITION.set(CONDITION.get() - 1);
+    res
+}
+
+/// Runs the `EXIT` trap, if it is set.
+///
+/// The trap is removed first, so it only runs once, even if it calls `exit` itself.
+pub fn run_exit_trap() {
+    let cmds = TRAPS
+        .try_borrow_mut()
+        .ok()
+        .and_then(|mut traps| traps.remove("EXIT"));
+    if let Some(cmds) = cmds {
+        exec_trap(&cmds);
+    }
+}
+
+/// Exits the shell with `status`, after running the `EXIT` trap
+pub fn exit(status: i32) -> ! {
+    LAST_STATUS.set(status);
+    run_exit_trap();
+    io::flush_output();
+    crate::exit(status)
+}
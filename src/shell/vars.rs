@@ -0,0 +1,89 @@
+use core::cell::{LazyCell, RefCell};
+
+use alloc::{
+    borrow::ToOwned,
+    collections::BTreeMap,
+    string::String,
+    vec::Vec,
+};
+
+/// The value of a variable, which is either a string or an indexed array
+#[derive(Clone)]
+pub enum Value {
+    Scalar(String),
+    Array(Vec<String>),
+}
+
+#[thread_local]
+static VARS: LazyCell<RefCell<BTreeMap<String, Value>>> = LazyCell::new(|| {
+    RefCell::new(
+        crate::start::vars()
+            .map(|(key, val)| (key.to_owned(), Value::Scalar(val.to_owned())))
+            .collect(),
+    )
+});
+
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c == '_' || c.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+    chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+/// Splits a subscripted name like `name[1]` into the name and the subscript
+pub fn split_subscript(name: &str) -> Option<(&str, &str)> {
+    let (base, rest) = name.split_once('[')?;
+    let subscript = rest.strip_suffix(']')?;
+    is_valid_name(base).then_some((base, subscript))
+}
+
+/// Gets the value of a variable, which is the first element of an array
+pub fn get(name: &str) -> Option<String> {
+    match VARS.borrow().get(name)? {
+        Value::Scalar(value) => Some(value.clone()),
+        Value::Array(values) => values.first().cloned(),
+    }
+}
+
+/// Updates the state that the shell keeps for a variable it uses itself, after it is set
+fn changed(name: &str) {
+    if name == "PATH" {
+        super::path::reset();
+    }
+}
+
+/// Sets the value of a variable, or the first element if it is an array
+pub fn set(name: &str, value: String) {
+    let mut vars = VARS.borrow_mut();
+    match vars.get_mut(name) {
+        Some(Value::Array(values)) if !values.is_empty() => values[0] = value,
+        Some(Value::Array(values)) => values.push(value),
+        _ => {
+            vars.insert(name.to_owned(), Value::Scalar(value));
+        }
+    }
+    drop(vars);
+    changed(name);
+}
+
+/// Gets the elements of an array, where a string is an array of one element
+pub fn get_array(name: &str) -> Option<Vec<String>> {
+    match VARS.borrow().get(name)? {
+        Value::Scalar(value) => Some(alloc::vec![value.clone()]),
+        Value::Array(values) => Some(values.clone()),
+    }
+}
+
+pub fn set_array(name: &str, values: Vec<String>) {
+    VARS.borrow_mut().insert(name.to_owned(), Value::Array(values));
+    changed(name);
+}
+
+/// Calls `f` with the name and value of every variable, in order of name
+pub fn for_each(mut f: impl FnMut(&str, &Value)) {
+    for (name, value) in VARS.borrow().iter() {
+        f(name, value);
+    }
+}
@@ -0,0 +1,398 @@
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use super::{
+    arith,
+    pattern::Pattern,
+    quote::{double_quoted, quote, quote_all},
+    regex,
+    vars,
+};
+use crate::{helpers::SplitOnceOwned, io};
+
+const DEFAULT_IFS: &str = " \t\n";
+
+/// An expansion within a word, which is evaluated when the word is expanded
+#[derive(Clone)]
+pub enum Expansion<'a> {
+    /// `$name` or `${name}`
+    Param(Cow<'a, str>),
+    /// `$(( expr ))`
+    Arith(Cow<'a, str>),
+}
+
+impl Expansion<'_> {
+    fn into_owned(self) -> Expansion<'static> {
+        match self {
+            Expansion::Param(name) => Expansion::Param(Cow::Owned(name.into_owned())),
+            Expansion::Arith(expr) => Expansion::Arith(Cow::Owned(expr.into_owned())),
+        }
+    }
+
+    fn value(&self) -> io::Result<String> {
+        match self {
+            Expansion::Param(name) => match vars::split_subscript(name) {
+                Some((name, sub @ ("@" | "*"))) => {
+                    // `${name[*]}` joins the elements with the first character of `IFS`
+                    let ifs = vars::get("IFS");
+                    let sep = match ifs.as_deref() {
+                        Some(ifs) if sub == "*" => {
+                            &ifs[..ifs.chars().next().map_or(0, char::len_utf8)]
+                        }
+                        _ => " ",
+                    };
+                    Ok(vars::get_array(name).unwrap_or_default().join(sep))
+                }
+                Some((name, sub)) => {
+                    let index = arith::eval(sub)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                    let values = vars::get_array(name).unwrap_or_default();
+                    // A negative index counts back from the end of the array
+                    let index = if index < 0 {
+                        values.len().checked_sub(index.unsigned_abs() as usize)
+                    } else {
+                        Some(index as usize)
+                    };
+                    Ok(index
+                        .and_then(|index| values.into_iter().nth(index))
+                        .unwrap_or_default())
+                }
+                None => Ok(param(name).unwrap_or_default()),
+            },
+            Expansion::Arith(expr) => arith::eval(expr)
+                .map(|val| val.to_string())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)),
+        }
+    }
+
+    /// The elements of `${name[@]}`, which are separate fields when the expansion is quoted
+    fn elements(&self) -> Option<Vec<String>> {
+        match self {
+            Expansion::Param(name) => match vars::split_subscript(name)? {
+                (name, "@") => Some(vars::get_array(name).unwrap_or_default()),
+                _ => None,
+            },
+            Expansion::Arith(_) => None,
+        }
+    }
+}
+
+/// Looks up the value of the parameter `name`, which may be a variable or a special parameter like `$0`
+pub fn param(name: &str) -> Option<String> {
+    match name {
+        "0" => crate::start::args().next().map(Into::into),
+        "?" => Some(super::last_status().to_string()),
+        name => vars::get(name),
+    }
+}
+
+#[derive(Clone)]
+pub enum WordPart<'a> {
+    /// Unquoted text, subject to brace and tilde expansion
+    Literal(Cow<'a, str>),
+    /// Text that was quoted or escaped, and is used as-is
+    Quoted(Cow<'a, str>),
+    /// An expansion, and whether it appeared inside double quotes.
+    ///
+    /// The result of an unquoted expansion is split into fields on `IFS`.
+    Expansion(Expansion<'a>, bool),
+}
+
+/// A shell word, which remembers which of its characters were quoted and where expansions appear
+#[derive(Clone)]
+pub struct Word<'a> {
+    pub parts: Vec<WordPart<'a>>,
+}
+
+impl<'a> Word<'a> {
+    pub fn literal(lit: Cow<'a, str>) -> Self {
+        Self {
+            parts: alloc::vec![WordPart::Literal(lit)],
+        }
+    }
+
+    /// Copies any text that the word borrows, so that it no longer refers to the input it was parsed from
+    pub fn into_owned(self) -> Word<'static> {
+        let parts = self
+            .parts
+            .into_iter()
+            .map(|part| match part {
+                WordPart::Literal(lit) => WordPart::Literal(Cow::Owned(lit.into_owned())),
+                WordPart::Quoted(lit) => WordPart::Quoted(Cow::Owned(lit.into_owned())),
+                WordPart::Expansion(expansion, quoted) => {
+                    WordPart::Expansion(expansion.into_owned(), quoted)
+                }
+            })
+            .collect();
+        Word { parts }
+    }
+
+    /// Returns the word's text if it is entirely unquoted and contains no expansions
+    pub fn as_literal(&self) -> Option<&str> {
+        match &*self.parts {
+            [WordPart::Literal(lit)] => Some(lit),
+            _ => None,
+        }
+    }
+
+    /// Returns the expression of an `(( expr ))` arithmetic command
+    pub fn arith_command(&self) -> Option<&str> {
+        self.as_literal()?.strip_prefix("((")?.strip_suffix("))")
+    }
+
+    /// Splits a `NAME=value` word into the name and the value.
+    ///
+    /// Only an unquoted prefix where `NAME` is a valid identifier makes an assignment.
+    pub fn split_assignment(mut self) -> Result<(Cow<'a, str>, Self), Self> {
+        let Some(WordPart::Literal(first)) = self.parts.first_mut() else {
+            return Err(self);
+        };
+        match first.find('=') {
+            Some(n) if vars::is_valid_name(&first[..n]) => {
+                let val = first.split_once_take("=").unwrap();
+                let key = core::mem::replace(first, val);
+                Ok((key, self))
+            }
+            _ => Err(self),
+        }
+    }
+
+    /// Expands the word into a single string, without field splitting
+    pub fn expand(&self) -> io::Result<String> {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                WordPart::Literal(lit) | WordPart::Quoted(lit) => out.push_str(lit),
+                WordPart::Expansion(exp, _) => out.push_str(&exp.value()?),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Expands the word into a pattern, where only the unquoted characters and the results of unquoted expansions are special
+    pub fn expand_pattern(&self) -> io::Result<Pattern> {
+        let mut pat = Pattern::new();
+        for part in &self.parts {
+            match part {
+                WordPart::Literal(lit) => pat.push_str(lit, false),
+                WordPart::Quoted(lit) => pat.push_str(lit, true),
+                WordPart::Expansion(exp, quoted) => pat.push_str(&exp.value()?, *quoted),
+            }
+        }
+        Ok(pat)
+    }
+
+    /// Expands the word into a regular expression, where quoted characters and the results of quoted expansions only match themselves
+    pub fn expand_regex(&self) -> io::Result<String> {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                WordPart::Literal(lit) => out.push_str(lit),
+                WordPart::Quoted(lit) => out.push_str(&regex::escape(lit)),
+                WordPart::Expansion(exp, true) => out.push_str(&regex::escape(&exp.value()?)),
+                WordPart::Expansion(exp, false) => out.push_str(&exp.value()?),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Expands the word into fields, splitting the results of unquoted expansions on `IFS`.
+    ///
+    /// A word that expands to nothing produces no fields, unless part of it was quoted.
+    pub fn expand_fields(&self, fields: &mut Vec<String>) -> io::Result<()> {
+        let ifs = vars::get("IFS");
+        let ifs = ifs.as_deref().unwrap_or(DEFAULT_IFS);
+
+        let mut cur = String::new();
+        // Whether `cur` is a field even if it is empty
+        let mut keep = false;
+        for part in &self.parts {
+            match part {
+                WordPart::Literal(lit) => cur.push_str(lit),
+                WordPart::Quoted(lit) => {
+                    cur.push_str(lit);
+                    keep = true;
+                }
+                WordPart::Expansion(exp, true) => match exp.elements() {
+                    Some(elements) => {
+                        let mut elements = elements.into_iter();
+                        if let Some(first) = elements.next() {
+                            cur.push_str(&first);
+                            keep = true;
+                        }
+                        for element in elements {
+                            fields.push(core::mem::replace(&mut cur, element));
+                        }
+                    }
+                    None => {
+                        cur.push_str(&exp.value()?);
+                        keep = true;
+                    }
+                },
+                WordPart::Expansion(exp, false) => {
+                    split_fields(&exp.value()?, ifs, fields, &mut cur, &mut keep)
+                }
+            }
+        }
+        if keep || !cur.is_empty() {
+            fields.push(cur);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SplitState {
+    /// Nothing has been split off yet, and the current field is empty
+    Start,
+    InField,
+    /// A field was just ended by `IFS` whitespace
+    AfterSpace,
+    /// A field was just ended by a non-whitespace `IFS` character
+    AfterDelim,
+}
+
+/// Splits `val` on `ifs`, appending to the current field `cur` and pushing completed fields.
+///
+/// Runs of `IFS` whitespace separate fields, while each other `IFS` character ends a field, even an empty one.
+fn split_fields(
+    val: &str,
+    ifs: &str,
+    fields: &mut Vec<String>,
+    cur: &mut String,
+    keep: &mut bool,
+) {
+    let mut state = if cur.is_empty() && !*keep {
+        SplitState::Start
+    } else {
+        SplitState::InField
+    };
+
+    for c in val.chars() {
+        if !ifs.contains(c) {
+            cur.push(c);
+            state = SplitState::InField;
+            continue;
+        }
+
+        let ends_field = if c.is_whitespace() {
+            state == SplitState::InField
+        } else {
+            state != SplitState::AfterSpace
+        };
+        if ends_field {
+            fields.push(core::mem::take(cur));
+            *keep = false;
+        }
+        state = match (c.is_whitespace(), state) {
+            (true, SplitState::Start) => SplitState::Start,
+            (true, SplitState::AfterDelim) => SplitState::AfterDelim,
+            (true, _) => SplitState::AfterSpace,
+            (false, _) => SplitState::AfterDelim,
+        };
+    }
+}
+
+impl<'a> core::fmt::Display for Word<'a> {
+    /// Writes the word in a form that is read back as the same word
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // A word that is entirely quoted text can be written in its shortest form
+        if self
+            .parts
+            .iter()
+            .all(|part| matches!(part, WordPart::Quoted(_)))
+        {
+            let text = self
+                .parts
+                .iter()
+                .map(|part| match part {
+                    WordPart::Quoted(lit) => &**lit,
+                    _ => unreachable!(),
+                })
+                .collect::<String>();
+            return f.write_str(&quote(&text));
+        }
+
+        let mut after_dollar = false;
+        for part in &self.parts {
+            match part {
+                WordPart::Literal(lit) => f.write_str(lit)?,
+                // A `$` that didn't start an expansion would start one if `'...'` followed it
+                WordPart::Quoted(lit) if after_dollar => f.write_str(&double_quoted(lit))?,
+                WordPart::Quoted(lit) => f.write_str(&quote_all(lit))?,
+                WordPart::Expansion(exp, quoted) => {
+                    let q = if *quoted { "\"" } else { "" };
+                    match exp {
+                        Expansion::Param(name) => write!(f, "{q}${{{name}}}{q}")?,
+                        Expansion::Arith(expr) => write!(f, "{q}$(({expr})){q}")?,
+                    }
+                }
+            }
+            after_dollar = matches!(part, WordPart::Literal(lit) if lit.ends_with('$'));
+        }
+        Ok(())
+    }
+}
+
+/// Accumulates the parts of a word, merging adjacent characters with the same quoting into one part
+pub(crate) struct WordBuilder<'a> {
+    parts: Vec<WordPart<'a>>,
+    buf: String,
+    buf_quoted: bool,
+    /// Set when the word contained quotes, so that `""` still produces an (empty) word
+    quoted: bool,
+}
+
+impl<'a> WordBuilder<'a> {
+    pub fn new() -> Self {
+        Self {
+            parts: Vec::new(),
+            buf: String::new(),
+            buf_quoted: false,
+            quoted: false,
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.buf.is_empty() {
+            let text = Cow::Owned(core::mem::take(&mut self.buf));
+            self.parts.push(if self.buf_quoted {
+                WordPart::Quoted(text)
+            } else {
+                WordPart::Literal(text)
+            });
+        }
+    }
+
+    pub fn push(&mut self, c: char, quoted: bool) {
+        if quoted != self.buf_quoted {
+            self.flush();
+            self.buf_quoted = quoted;
+        }
+        self.buf.push(c);
+    }
+
+    pub fn push_part(&mut self, part: WordPart<'a>) {
+        self.flush();
+        self.parts.push(part);
+    }
+
+    pub fn mark_quoted(&mut self) {
+        self.quoted = true;
+    }
+
+    pub fn finish(mut self) -> Word<'a> {
+        self.flush();
+        if self.parts.is_empty() {
+            self.parts.push(if self.quoted {
+                WordPart::Quoted(Cow::Borrowed(""))
+            } else {
+                WordPart::Literal(Cow::Borrowed(""))
+            });
+        }
+        Word { parts: self.parts }
+    }
+}
@@ -3,7 +3,9 @@ use core::{
     ffi::{CStr, c_char},
 };
 
-use crate::{eprintln, helpers::AssertThreadSafe, println};
+use alloc::string::String;
+
+use crate::{eprintln, helpers::AssertThreadSafe, io};
 
 pub trait Termination {
     fn report(self) -> i32;
@@ -59,13 +61,33 @@ static ENV: AssertThreadSafe<OnceCell<*mut *mut c_char>> =
 
 #[unsafe(export_name = "main")]
 unsafe extern "C" fn sys_main(argc: isize, argv: *mut *mut c_char, envp: *mut *mut c_char) -> i32 {
-    println!("{argc}");
     let _ = ARGS.set((argc as usize, argv));
     let _ = ENV.set(envp);
     if argc > 0 {
         let _ = PRG_NAME.set(unsafe { CStr::from_ptr(argv.read()) });
     }
-    Termination::report(crate::main())
+    let status = Termination::report(crate::main());
+    crate::io::flush_output();
+    status
+}
+
+pub struct Args(core::ops::Range<usize>, *mut *mut c_char);
+
+pub fn args() -> Args {
+    let (argc, argv) = ARGS.get().copied().unwrap();
+    Args(0..argc, argv)
+}
+
+impl Iterator for Args {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.0.next()?;
+        let cstr = unsafe { CStr::from_ptr(self.1.add(n).read()) };
+
+        // Arguments such as file names don't have to be UTF-8, so their bytes are kept as the shell's strings keep them
+        Some(io::decode_bytes(cstr.to_bytes()))
+    }
 }
 
 pub struct Vars(*mut *mut c_char);
@@ -94,7 +116,3 @@ impl Iterator for Vars {
         Some((var, val))
     }
 }
-
-pub fn var(var: &str) -> Option<&'static str> {
-    vars().find_map(|(key, val)| (key == var).then_some(val))
-}